@@ -0,0 +1,118 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::result;
+
+pub type Result<T> = result::Result<T, Error>;
+
+// ********************************************************
+// chunk: Chunk Type (ffi Constants), 0 When Unknown
+// offset: Byte Offset Of The Chunk/Field In The Stream, 0 When Unknown
+// ********************************************************
+#[derive(Debug)]
+pub enum Error {
+	// First 8 Bytes Are Not The PNG Signature
+	InvalidSignature { offset: u64 },
+	// IHDR Field Out Of Range (Width, Height, Bit Depth, ...)
+	InvalidHeader { chunk: u32, offset: u64, field: &'static str },
+	// Chunk Contents Are Malformed (Filter Type, Palette Index, ...)
+	InvalidChunk { chunk: u32, offset: u64, reason: &'static str },
+	CrcMismatch { chunk: u32, offset: u64, expected: u32, found: u32 },
+	Unsupported { chunk: u32, offset: u64, feature: &'static str },
+	// A DecodeLimits Bound Would Be Exceeded (Width, Height, Pixels, Bytes Allocated, Metadata Bytes)
+	LimitExceeded { chunk: u32, offset: u64, limit: &'static str },
+	Truncated { chunk: u32, offset: u64 },
+	// Caller-Supplied Image Buffer, Dimensions Or Rows Don't Fit Together (Not From A File)
+	InvalidImage { reason: &'static str },
+	Zlib { chunk: u32, offset: u64, message: String },
+	Io { chunk: u32, offset: u64, error: io::Error },
+}
+
+impl Error {
+	// End Of Stream While Reading A Chunk Is Truncated Data, Everything Else Is I/O
+	pub(crate) fn from_io(error: io::Error, chunk: u32, offset: u64) -> Error {
+		match error.kind() {
+			io::ErrorKind::UnexpectedEof => Error::Truncated { chunk, offset },
+			_ => Error::Io { chunk, offset, error },
+		}
+	}
+
+	pub fn chunk(&self) -> u32 {
+		match *self {
+			Error::InvalidSignature { .. } | Error::InvalidImage { .. } => 0,
+			Error::InvalidHeader { chunk, .. } |
+			Error::InvalidChunk { chunk, .. } |
+			Error::CrcMismatch { chunk, .. } |
+			Error::Unsupported { chunk, .. } |
+			Error::LimitExceeded { chunk, .. } |
+			Error::Truncated { chunk, .. } |
+			Error::Zlib { chunk, .. } |
+			Error::Io { chunk, .. } => chunk,
+		}
+	}
+
+	pub fn offset(&self) -> u64 {
+		match *self {
+			Error::InvalidImage { .. } => 0,
+			Error::InvalidSignature { offset } |
+			Error::InvalidHeader { offset, .. } |
+			Error::InvalidChunk { offset, .. } |
+			Error::CrcMismatch { offset, .. } |
+			Error::Unsupported { offset, .. } |
+			Error::LimitExceeded { offset, .. } |
+			Error::Truncated { offset, .. } |
+			Error::Zlib { offset, .. } |
+			Error::Io { offset, .. } => offset,
+		}
+	}
+}
+
+fn chunk_name(chunk: u32) -> String {
+	let tag = [(chunk >> 24) as u8, (chunk >> 16) as u8, (chunk >> 8) as u8, chunk as u8];
+	String::from_utf8_lossy(&tag).into_owned()
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::InvalidSignature { offset } => write!(f, "Invalid PNG Signature At Byte {}", offset),
+			Error::InvalidHeader { chunk, offset, field } => write!(f, "Invalid {} {} At Byte {}", chunk_name(chunk), field, offset),
+			Error::InvalidChunk { chunk, offset, reason } => write!(f, "{} In {} Chunk At Byte {}", reason, chunk_name(chunk), offset),
+			Error::CrcMismatch { chunk, offset, expected, found } => {
+				write!(f, "CRC Mismatch In {} Chunk At Byte {}: Expected {:08X}, Found {:08X}", chunk_name(chunk), offset, expected, found)
+			},
+			Error::Unsupported { chunk, offset, feature } => write!(f, "{} Not Supported In {} Chunk At Byte {}", feature, chunk_name(chunk), offset),
+			Error::LimitExceeded { chunk, offset, limit } => write!(f, "{} Limit Exceeded In {} Chunk At Byte {}", limit, chunk_name(chunk), offset),
+			Error::Truncated { chunk, offset } => write!(f, "Truncated {} Chunk At Byte {}", chunk_name(chunk), offset),
+			Error::InvalidImage { reason } => write!(f, "Invalid Image: {}", reason),
+			Error::Zlib { chunk, offset, ref message } => write!(f, "Zlib Error In {} Chunk At Byte {}: {}", chunk_name(chunk), offset, message),
+			Error::Io { ref error, .. } => write!(f, "I/O Error: {}", error),
+		}
+	}
+}
+
+impl error::Error for Error {
+	fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+		match *self {
+			Error::Io { ref error, .. } => Some(error),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for Error {
+	fn from(error: io::Error) -> Error {
+		Error::Io { chunk: 0, offset: 0, error }
+	}
+}
+
+// Keeps Callers Working With io::Result
+impl From<Error> for io::Error {
+	fn from(error: Error) -> io::Error {
+		match error {
+			Error::Io { error, .. } => error,
+			Error::Truncated { .. } => io::Error::new(io::ErrorKind::UnexpectedEof, error.to_string()),
+			_ => io::Error::new(io::ErrorKind::InvalidData, error.to_string()),
+		}
+	}
+}
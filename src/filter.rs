@@ -0,0 +1,155 @@
+use error::Result;
+use zlib::{self, ZlibStrategy};
+
+// ********************************************************
+// Scanline Filters, One Implementation For Every Color Type And Depth
+// bpp: Bytes Per Complete Pixel, Rounded Up To 1 For Sub Byte Depths
+// prev: The Previous Unfiltered Scanline, Empty For The First Row (All Zeros)
+// ********************************************************
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterType {
+	None = 0,
+	Sub = 1,
+	Up = 2,
+	Avg = 3,
+	Paeth = 4,
+}
+
+pub const FILTER_TYPES: [FilterType; 5] = [FilterType::None, FilterType::Sub, FilterType::Up, FilterType::Avg, FilterType::Paeth];
+
+impl FilterType {
+	pub fn from_u8(filter_type: u8) -> Option<FilterType> {
+		FILTER_TYPES.get(filter_type as usize).cloned()
+	}
+}
+
+// ***************************************************************
+// How The Encoder Picks A Filter For Each Scanline
+// Ties Prefer The Earlier Filter In FILTER_TYPES
+// ***************************************************************
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FilterStrategy {
+	// Same Filter On Every Row
+	Fixed(FilterType),
+	// Smallest Sum Of Filtered Bytes Taken As Signed (libpng Default)
+	#[default]
+	MinSum,
+	// Smallest Shannon Entropy Of The Filtered Bytes
+	Entropy,
+	// Deflates Every Candidate, Smallest Output But Five Deflates Per Row
+	BruteForce,
+}
+
+// ***************************************************************
+// a: Byte bpp To The Left, b: Byte Above, c: Byte Above a
+// ***************************************************************
+fn get_predictor(filter_type: FilterType, a: u8, b: u8, c: u8) -> u8 {
+	match filter_type {
+		FilterType::None => 0,
+		FilterType::Sub => a,
+		FilterType::Up => b,
+		// floor((a + b) / 2) Without Overflowing u8
+		FilterType::Avg => ((a as u16 + b as u16) / 2) as u8,
+		FilterType::Paeth => get_paeth_predictor(a, b, c),
+	}
+}
+
+// Ties Prefer a, Then b, Then c
+fn get_paeth_predictor(a: u8, b: u8, c: u8) -> u8 {
+	let p = a as i16 + b as i16 - c as i16;
+	let pa = (p - a as i16).abs();
+	let pb = (p - b as i16).abs();
+	let pc = (p - c as i16).abs();
+	if pa <= pb && pa <= pc {
+		a
+	} else if pb <= pc {
+		b
+	} else {
+		c
+	}
+}
+
+fn get_above(prev: &[u8], i: usize) -> u8 {
+	if prev.is_empty() { 0 } else { prev[i] }
+}
+
+// ***************************************************************
+// Filt(x) = Orig(x) - Predictor(Orig(a), Orig(b), Orig(c))
+// Appends The Filter Type Byte Followed By The Filtered Row
+// ***************************************************************
+pub fn filter_row(filter_type: FilterType, bpp: usize, prev: &[u8], row: &[u8], out: &mut Vec<u8>) {
+	out.push(filter_type as u8);
+	for (i, &x) in row.iter().enumerate() {
+		let (a, c) = if i >= bpp { (row[i - bpp], get_above(prev, i - bpp)) } else { (0, 0) };
+		out.push(x.wrapping_sub(get_predictor(filter_type, a, get_above(prev, i), c)));
+	}
+}
+
+// ***************************************************************
+// Recon(x) = Filt(x) + Predictor(Recon(a), Recon(b), Recon(c))
+// Reconstructs row In Place
+// ***************************************************************
+pub fn unfilter_row(filter_type: FilterType, bpp: usize, prev: &[u8], row: &mut [u8]) {
+	if filter_type == FilterType::None {
+		return;
+	}
+	for i in 0..row.len() {
+		let (a, c) = if i >= bpp { (row[i - bpp], get_above(prev, i - bpp)) } else { (0, 0) };
+		row[i] = row[i].wrapping_add(get_predictor(filter_type, a, get_above(prev, i), c));
+	}
+}
+
+fn get_sum_of_abs(filterd: &[u8]) -> f64 {
+	filterd.iter().map(|&b| std::cmp::min(b, b.wrapping_neg()) as u64).sum::<u64>() as f64
+}
+
+fn get_entropy(filterd: &[u8]) -> f64 {
+	let mut counts = [0usize; 256];
+	for &b in filterd {
+		counts[b as usize] += 1;
+	}
+	let total = filterd.len() as f64;
+	counts.iter().filter(|&&count| count > 0).map(|&count| {
+		let p = count as f64 / total;
+		-p * p.log2()
+	}).sum()
+}
+
+fn get_deflated_size(filterd: &[u8]) -> Result<f64> {
+	match zlib::get_deflated(filterd, zlib::MAX_LEVEL, ZlibStrategy::Default) {
+		Ok(deflated) => Ok(deflated.len() as f64),
+		Err(e) => Err(e),
+	}
+}
+
+// ***************************************************************
+// Filters row With The Filter strategy Picks, Appends Like filter_row
+// Only BruteForce Can Fail, When Deflating A Candidate Does
+// ***************************************************************
+pub fn filter_row_with_strategy(strategy: FilterStrategy, bpp: usize, prev: &[u8], row: &[u8], out: &mut Vec<u8>) -> Result<FilterType> {
+	if let FilterStrategy::Fixed(filter_type) = strategy {
+		filter_row(filter_type, bpp, prev, row, out);
+		return Ok(filter_type);
+	}
+
+	let mut best: Option<(Vec<u8>, f64)> = None;
+	for &filter_type in FILTER_TYPES.iter() {
+		let mut filterd = Vec::<u8>::with_capacity(row.len() + 1);
+		filter_row(filter_type, bpp, prev, row, &mut filterd);
+		let cost = match strategy {
+			FilterStrategy::Entropy => get_entropy(&filterd[1..]),
+			FilterStrategy::BruteForce => match get_deflated_size(&filterd[1..]) {
+				Ok(size) => size,
+				Err(e) => return Err(e),
+			},
+			_ => get_sum_of_abs(&filterd[1..]),
+		};
+		if best.as_ref().is_none_or(|&(_, best_cost)| cost < best_cost) {
+			best = Some((filterd, cost));
+		}
+	}
+	// FILTER_TYPES Is Never Empty
+	let filterd = best.unwrap().0;
+	out.extend_from_slice(&filterd);
+	Ok(FILTER_TYPES[filterd[0] as usize])
+}
@@ -0,0 +1,205 @@
+use pixel::{get_gray, Pixel, Pixels, Rows};
+use super::{COLOR_TYPE_GRAY, COLOR_TYPE_RGB, COLOR_TYPE_PALETTE, COLOR_TYPE_GRAY_ALPHA, COLOR_TYPE_RGBA};
+
+// ********************************************************
+// Order Of The Samples Within One Pixel
+// ********************************************************
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ChannelLayout {
+	Gray,
+	GrayAlpha,
+	Rgb,
+	#[default]
+	Rgba,
+}
+
+impl ChannelLayout {
+	pub fn channels(self) -> usize {
+		match self {
+			ChannelLayout::Gray => 1,
+			ChannelLayout::GrayAlpha => 2,
+			ChannelLayout::Rgb => 3,
+			ChannelLayout::Rgba => 4,
+		}
+	}
+
+	pub fn has_alpha(self) -> bool {
+		self == ChannelLayout::GrayAlpha || self == ChannelLayout::Rgba
+	}
+
+	pub fn is_gray(self) -> bool {
+		self == ChannelLayout::Gray || self == ChannelLayout::GrayAlpha
+	}
+
+	// Palette Images Decode To Rgb, Or Rgba When tRNS Is Present
+	pub fn from_color_type(color_type: u8) -> Option<ChannelLayout> {
+		match color_type {
+			COLOR_TYPE_GRAY => Some(ChannelLayout::Gray),
+			COLOR_TYPE_RGB | COLOR_TYPE_PALETTE => Some(ChannelLayout::Rgb),
+			COLOR_TYPE_GRAY_ALPHA => Some(ChannelLayout::GrayAlpha),
+			COLOR_TYPE_RGBA => Some(ChannelLayout::Rgba),
+			_ => None,
+		}
+	}
+
+	pub fn color_type(self) -> u8 {
+		match self {
+			ChannelLayout::Gray => COLOR_TYPE_GRAY,
+			ChannelLayout::GrayAlpha => COLOR_TYPE_GRAY_ALPHA,
+			ChannelLayout::Rgb => COLOR_TYPE_RGB,
+			ChannelLayout::Rgba => COLOR_TYPE_RGBA,
+		}
+	}
+}
+
+// ********************************************************
+// Interleaved 16bit Samples, Row After Row
+// stride: Samples From The Start Of One Row To The Next, At Least width * channels
+// Decoded Images Are Tightly Packed In The Layout Of Their Color Type
+// ********************************************************
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ImageBuffer {
+	pub width: u32,
+	pub height: u32,
+	pub layout: ChannelLayout,
+	pub stride: usize,
+	pub samples: Vec<u16>,
+}
+
+impl ImageBuffer {
+	// All Samples 0
+	pub fn new(width: u32, height: u32, layout: ChannelLayout) -> ImageBuffer {
+		let stride = width as usize * layout.channels();
+		ImageBuffer { width, height, layout, stride, samples: vec![0; stride * height as usize] }
+	}
+
+	// None Unless samples Holds Exactly width * height Pixels
+	pub fn from_samples(width: u32, height: u32, layout: ChannelLayout, samples: Vec<u16>) -> Option<ImageBuffer> {
+		let stride = width as usize * layout.channels();
+		if samples.len() != stride * height as usize {
+			return None;
+		}
+		Some(ImageBuffer { width, height, layout, stride, samples })
+	}
+
+	pub fn channels(&self) -> usize {
+		self.layout.channels()
+	}
+
+	pub fn row(&self, y: u32) -> &[u16] {
+		assert!(y < self.height, "Row {} Out Of Bounds For Height {}", y, self.height);
+		let start = y as usize * self.stride;
+		&self.samples[start..start + self.width as usize * self.channels()]
+	}
+
+	pub fn row_mut(&mut self, y: u32) -> &mut [u16] {
+		assert!(y < self.height, "Row {} Out Of Bounds For Height {}", y, self.height);
+		let start = y as usize * self.stride;
+		let end = start + self.width as usize * self.channels();
+		&mut self.samples[start..end]
+	}
+
+	// Panics Outside width x height Instead Of Reaching The Next Row Or Its Padding
+	fn get_pixel_start(&self, x: u32, y: u32) -> usize {
+		assert!(x < self.width && y < self.height, "Pixel ({}, {}) Out Of Bounds For {}x{} Image", x, y, self.width, self.height);
+		y as usize * self.stride + x as usize * self.channels()
+	}
+
+	pub fn pixel(&self, x: u32, y: u32) -> &[u16] {
+		let start = self.get_pixel_start(x, y);
+		&self.samples[start..start + self.channels()]
+	}
+
+	pub fn pixel_mut(&mut self, x: u32, y: u32) -> &mut [u16] {
+		let start = self.get_pixel_start(x, y);
+		let end = start + self.channels();
+		&mut self.samples[start..end]
+	}
+
+	// ********************************************************
+	// Any Layout As [R, G, B, A]
+	// Gray Is Copied Into R, G And B, No Alpha Is Fully Opaque
+	// ********************************************************
+	pub fn get_rgba(&self, x: u32, y: u32) -> [u16; 4] {
+		let p = self.pixel(x, y);
+		match self.layout {
+			ChannelLayout::Gray => [p[0], p[0], p[0], 0xFFFF],
+			ChannelLayout::GrayAlpha => [p[0], p[0], p[0], p[1]],
+			ChannelLayout::Rgb => [p[0], p[1], p[2], 0xFFFF],
+			ChannelLayout::Rgba => [p[0], p[1], p[2], p[3]],
+		}
+	}
+
+	// Color To Gray Takes The Rounded Mean Of R, G And B
+	pub fn set_rgba(&mut self, x: u32, y: u32, rgba: [u16; 4]) {
+		let layout = self.layout;
+		let gray = get_gray(rgba);
+		let p = self.pixel_mut(x, y);
+		match layout {
+			ChannelLayout::Gray => p[0] = gray,
+			ChannelLayout::GrayAlpha => p.copy_from_slice(&[gray, rgba[3]]),
+			ChannelLayout::Rgb => p.copy_from_slice(&rgba[..3]),
+			ChannelLayout::Rgba => p.copy_from_slice(&rgba),
+		}
+	}
+
+	// ********************************************************
+	// Typed Pixels, Any Pixel Type Works With Any Layout
+	// put_pixel Stores Into layout, Color Or Alpha It Lacks Is Dropped
+	// Both Panic Outside width x height Like pixel()
+	// ********************************************************
+	pub fn get_pixel<P: Pixel>(&self, x: u32, y: u32) -> P {
+		P::from_rgba16(self.get_rgba(x, y))
+	}
+
+	pub fn put_pixel<P: Pixel>(&mut self, x: u32, y: u32, pixel: P) {
+		self.set_rgba(x, y, pixel.to_rgba16());
+	}
+
+	pub fn pixels<P: Pixel>(&self) -> Pixels<'_, P> {
+		Pixels::new(self, 0, self.height)
+	}
+
+	pub fn rows<P: Pixel>(&self) -> Rows<'_, P> {
+		Rows::new(self)
+	}
+
+	pub fn to_layout(&self, layout: ChannelLayout) -> ImageBuffer {
+		let mut image = ImageBuffer::new(self.width, self.height, layout);
+		for y in 0..self.height {
+			for x in 0..self.width {
+				image.set_rgba(x, y, self.get_rgba(x, y));
+			}
+		}
+		image
+	}
+
+	// ********************************************************
+	// Planar Views, Copied Out Without Row Padding
+	// ********************************************************
+	// One Channel Of Every Pixel, channel < channels()
+	pub fn plane(&self, channel: usize) -> Vec<u16> {
+		let channels = self.channels();
+		assert!(channel < channels, "Channel {} Out Of Bounds For {} Channels", channel, channels);
+		(0..self.height).flat_map(|y| self.row(y).iter().skip(channel).step_by(channels).cloned()).collect()
+	}
+
+	// Three Samples Per Pixel, Gray Is Copied Into All Three
+	pub fn rgb(&self) -> Vec<u16> {
+		let mut rgb = Vec::<u16>::with_capacity(3 * self.width as usize * self.height as usize);
+		for y in 0..self.height {
+			for x in 0..self.width {
+				rgb.extend_from_slice(&self.get_rgba(x, y)[..3]);
+			}
+		}
+		rgb
+	}
+
+	// One Sample Per Pixel, 0xFFFF Without An Alpha Channel
+	pub fn alpha(&self) -> Vec<u16> {
+		if !self.layout.has_alpha() {
+			return vec![0xFFFF; self.width as usize * self.height as usize];
+		}
+		self.plane(self.channels() - 1)
+	}
+}
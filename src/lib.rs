@@ -1,26 +1,55 @@
 #![crate_name = "png16"]
 #![crate_type = "rlib"]
 #![allow(dead_code)]
+#![allow(clippy::question_mark)]
 
 extern crate byteorder;
 extern crate inflate;
 extern crate flate2;
+extern crate miniz_sys;
 extern crate crc;
 
-use byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
-use crc::crc32;
-use flate2::{Compression, FlateReadExt};
+use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt, BigEndian};
+use crc::crc32::{self, Hasher32};
+use flate2::FlateReadExt;
+use std::borrow::Cow;
 use std::fs::File;
-use std::io::{Result, Error, ErrorKind, SeekFrom, BufReader, BufWriter};
+use std::io::{BufReader, BufWriter};
 use std::io::prelude::*;
-use std::num::Wrapping;
 use std::str;
 pub mod ffi;
+pub mod filter;
+mod error;
+mod image;
+mod metadata;
+mod pixel;
+mod stream;
+mod text;
+mod zlib;
+
+pub use error::{Error, Result};
+pub use image::{ImageBuffer, ChannelLayout};
+pub use pixel::{Pixel, Pixels, Rows, Rgba16, Rgb16, LumaA16, Luma16, Rgba8, Rgb8, LumaA8, Luma8};
+pub use metadata::{Metadata, Chromaticities, RenderingIntent, IccProfile, PhysicalDimensions, Timestamp};
+pub use stream::{RowDecoder, RowEncoder};
+pub use text::{TextChunk, TextKind};
+pub use zlib::ZlibStrategy;
+use filter::{FilterStrategy, FilterType};
 
 pub const DEPTH_16: u8 = 0x10;
 pub const DEPTH_8: u8 = 0x08;
 
-#[derive(Debug)]
+pub const COLOR_TYPE_GRAY: u8 = 0x00;
+pub const COLOR_TYPE_RGB: u8 = 0x02;
+pub const COLOR_TYPE_PALETTE: u8 = 0x03;
+pub const COLOR_TYPE_GRAY_ALPHA: u8 = 0x04;
+pub const COLOR_TYPE_RGBA: u8 = 0x06;
+
+// Largest Width/Height The PNG Spec Allows (2^31 - 1)
+pub const MAX_DIMENSION: u32 = 0x7FFFFFFF;
+
+#[derive(Debug, Default)]
+#[allow(non_camel_case_types)]
 pub struct PNG_IHDR {
 	pub total_bytes: u32,
 	pub width: u32,
@@ -36,8 +65,99 @@ pub struct PNG_IHDR {
 pub struct PNG {
 	pub ihdr: PNG_IHDR,
 	pub header: Vec<u8>,
-	pub rgb: Vec<u16>,
-	pub alpha: Vec<u16>,
+	// Chunks Between The Last IDAT And IEND Without A Typed Representation
+	pub trailer: Vec<u8>,
+	pub metadata: Metadata,
+	pub image: ImageBuffer,
+	pub warnings: Vec<Error>,
+}
+
+impl PNG {
+	// ********************************************************
+	// A PNG Ready To Encode Without Decoding A File First
+	// ihdr Is Filled In As If Decoded (16bit, Color Type Of The Layout, Not Interlaced)
+	// Metadata, header And trailer Start Empty
+	// ********************************************************
+	pub fn from_image(image: ImageBuffer) -> Result<PNG> {
+		match check_dimensions(image.width, image.height) {
+			Ok(_) => (),
+			Err(e) => return Err(e),
+		};
+		let row_samples = image.width as usize * image.channels();
+		if image.stride < row_samples || image.samples.len() < image.stride * (image.height as usize - 1) + row_samples {
+			return Err(Error::InvalidImage { reason: "Image Buffer Too Small" });
+		}
+
+		let mut ihdr = PNG_IHDR { total_bytes: 13, width: image.width, height: image.height, depth: DEPTH_16, color_type: image.layout.color_type(), ..Default::default() };
+		let chunk = get_encode_ihdr(&ihdr, ihdr.depth, ihdr.color_type, ihdr.interlace);
+		ihdr.crc = BigEndian::read_u32(&chunk[chunk.len() - 4..]);
+		Ok(PNG { ihdr, image, ..Default::default() })
+	}
+
+	// data Is Interleaved And Tightly Packed, Exactly width * height Pixels
+	pub fn from_rgba16(width: u32, height: u32, data: Vec<u16>) -> Result<PNG> {
+		PNG::from_samples(width, height, ChannelLayout::Rgba, data)
+	}
+
+	pub fn from_rgb16(width: u32, height: u32, data: Vec<u16>) -> Result<PNG> {
+		PNG::from_samples(width, height, ChannelLayout::Rgb, data)
+	}
+
+	pub fn from_gray_alpha16(width: u32, height: u32, data: Vec<u16>) -> Result<PNG> {
+		PNG::from_samples(width, height, ChannelLayout::GrayAlpha, data)
+	}
+
+	pub fn from_gray16(width: u32, height: u32, data: Vec<u16>) -> Result<PNG> {
+		PNG::from_samples(width, height, ChannelLayout::Gray, data)
+	}
+
+	fn from_samples(width: u32, height: u32, layout: ChannelLayout, data: Vec<u16>) -> Result<PNG> {
+		match check_dimensions(width, height) {
+			Ok(_) => (),
+			Err(e) => return Err(e),
+		};
+		match ImageBuffer::from_samples(width, height, layout, data) {
+			Some(image) => PNG::from_image(image),
+			None => Err(Error::InvalidImage { reason: "Image Buffer Length Does Not Match Size" }),
+		}
+	}
+
+	// Planar Views Of PNG.image, See ImageBuffer::rgb/alpha
+	pub fn rgb(&self) -> Vec<u16> {
+		self.image.rgb()
+	}
+
+	pub fn alpha(&self) -> Vec<u16> {
+		self.image.alpha()
+	}
+
+	// Typed Pixels Of PNG.image, See ImageBuffer::get_pixel
+	pub fn get_pixel<P: Pixel>(&self, x: u32, y: u32) -> P {
+		self.image.get_pixel(x, y)
+	}
+
+	pub fn put_pixel<P: Pixel>(&mut self, x: u32, y: u32, pixel: P) {
+		self.image.put_pixel(x, y, pixel)
+	}
+
+	pub fn pixels<P: Pixel>(&self) -> Pixels<'_, P> {
+		self.image.pixels()
+	}
+
+	pub fn rows<P: Pixel>(&self) -> Rows<'_, P> {
+		self.image.rows()
+	}
+}
+
+// Width And Height As parse_ihdr Accepts Them
+fn check_dimensions(width: u32, height: u32) -> Result<bool> {
+	if width == 0 || width > MAX_DIMENSION {
+		return Err(Error::InvalidImage { reason: "Width" });
+	}
+	if height == 0 || height > MAX_DIMENSION {
+		return Err(Error::InvalidImage { reason: "Height" });
+	}
+	Ok(true)
 }
 
 impl Default for PNG {
@@ -45,231 +165,618 @@ impl Default for PNG {
 		PNG {
 			ihdr: PNG_IHDR { ..Default::default() },
 			header: Vec::new(),
-			rgb: Vec::new(),
-			alpha: Vec::new(),
+			trailer: Vec::new(),
+			metadata: Metadata::default(),
+			image: ImageBuffer::default(),
+			warnings: Vec::new(),
 		}
 	}
 }
 
-impl Default for PNG_IHDR {
-	fn default() -> PNG_IHDR {
-		PNG_IHDR {
-			total_bytes: 0,
-			width: 0,
-			height: 0,
-			depth: 0,
-			color_type: 0,
-			compression: 0,
-			filter: 0,
-			interlace: 0,
-			crc: 0,
+// ********************************************************
+// What To Do When A Chunk CRC Does Not Match
+// Strict: Fail, Warn: Push The Error To PNG.warnings, Ignore: Skip The Check
+// ********************************************************
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CrcCheck {
+	Strict,
+	Warn,
+	Ignore,
+}
+
+// ********************************************************
+// Checked Before Anything Is Allocated, Exceeding One Fails With Error::LimitExceeded
+// max_bytes Covers The Compressed, Inflated And Decoded Buffers Together
+// ********************************************************
+#[derive(Debug, Clone)]
+pub struct DecodeLimits {
+	pub max_width: u32,
+	pub max_height: u32,
+	pub max_pixels: u64,
+	pub max_bytes: u64,
+	pub max_metadata_bytes: u64,
+}
+
+impl Default for DecodeLimits {
+	fn default() -> DecodeLimits {
+		DecodeLimits {
+			max_width: MAX_DIMENSION,
+			max_height: MAX_DIMENSION,
+			max_pixels: 1 << 29,
+			max_bytes: 1 << 32,
+			max_metadata_bytes: 1 << 24,
 		}
 	}
 }
 
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+	pub crc_check: CrcCheck,
+	pub limits: DecodeLimits,
+}
+
+impl Default for DecodeOptions {
+	fn default() -> DecodeOptions {
+		DecodeOptions { crc_check: CrcCheck::Strict, limits: DecodeLimits::default() }
+	}
+}
+
 pub fn decode_16bit_png(filepath: &str) -> Result<PNG> {
 	let img = match File::open(filepath) {
 		Ok(img) => img,
-		Err(e) => return Err(e),
+		Err(e) => return Err(Error::from(e)),
 	};
 
-	let mut png = PNG { ..Default::default() };
+	decode_16bit_png_from_reader(BufReader::new(img))
+}
 
-	let mut reader = BufReader::new(&img);
-	let mut reader_ref = reader.get_mut();
+pub fn decode_16bit_png_from_bytes(bytes: &[u8]) -> Result<PNG> {
+	decode_16bit_png_from_reader(bytes)
+}
+
+pub fn decode_16bit_png_from_reader<R: Read>(reader: R) -> Result<PNG> {
+	decode_16bit_png_with_options(reader, &DecodeOptions::default())
+}
 
-	png.ihdr = match parse_ihdr(&mut reader_ref) {
+// ********************************************************
+// Chunks Are Read Strictly In Order, No Seek Required
+// ********************************************************
+pub fn decode_16bit_png_with_options<R: Read>(reader: R, options: &DecodeOptions) -> Result<PNG> {
+	let mut png = PNG { ..Default::default() };
+	let mut reader = PositionReader { reader: Box::new(reader), offset: 0 };
+
+	png.ihdr = match parse_ihdr(&mut reader, options, &mut png.warnings) {
 		Ok(ihdr) => ihdr,
 		Err(e) => return Err(e),
 	};
 
-	png.header = match get_header(&mut reader_ref) {
+	match check_limits(&png.ihdr, &options.limits) {
+		Ok(_) => (),
+		Err(e) => return Err(e),
+	};
+
+	let idat_tag = match get_header(&mut reader, options, &mut png.warnings) {
+		Ok((header, idat_tag)) => {
+			png.header = header;
+			idat_tag
+		},
+		Err(e) => return Err(e),
+	};
+
+	// Typed Chunks Move Into PNG.metadata, Everything Else Stays In PNG.header
+	let mut inflate_budget = options.limits.max_metadata_bytes;
+	png.header = match metadata::get_metadata(&png.header, HEADER_OFFSET, &png.ihdr, &mut inflate_budget, &mut png.metadata, false, &mut png.warnings) {
 		Ok(header) => header,
 		Err(e) => return Err(e),
 	};
 
-	let mut rgba = match get_rgba(&mut reader_ref, png.ihdr.width, png.ihdr.depth) {
-		Ok(rgba) => rgba,
+	let idat_offset = reader.offset - 8;
+	let (raw, chunk_tag) = match get_idat(&mut reader, &png.ihdr, idat_tag, options, &mut png.warnings) {
+		Ok(idat) => idat,
 		Err(e) => return Err(e),
 	};
 
-	// Split RGBA Into Two Buffers
-	match get_rgb_a(&mut rgba, &mut png.rgb, &mut png.alpha) {
-		Ok(_) => (),
+	// tIME And Text After IDAT Keep Their Position, Everything Else Stays In PNG.trailer
+	let trailer_offset = reader.offset - 8;
+	png.trailer = match get_trailer(&mut reader, chunk_tag, png.header.len() as u64, options, &mut png.warnings) {
+		Ok(trailer) => trailer,
+		Err(e) => return Err(e),
+	};
+	png.trailer = match metadata::get_metadata(&png.trailer, trailer_offset, &png.ihdr, &mut inflate_budget, &mut png.metadata, true, &mut png.warnings) {
+		Ok(trailer) => trailer,
 		Err(e) => return Err(e),
 	};
 
+	// Samples Stay Interleaved In The Layout Of The Color Type
+	let (layout, samples) = if png.ihdr.color_type == COLOR_TYPE_PALETTE {
+		// Expand Palette Indices Through PLTE/tRNS
+		let (layout, palette) = match get_palette(&png.ihdr, &png.header, idat_offset) {
+			Ok(palette) => palette,
+			Err(e) => return Err(e),
+		};
+		match get_palette_samples(&raw, layout, &palette, idat_offset) {
+			Ok(samples) => (layout, samples),
+			Err(e) => return Err(e),
+		}
+	} else {
+		(get_layout(png.ihdr.color_type), get_samples(raw, png.ihdr.depth))
+	};
+	png.image = ImageBuffer { width: png.ihdr.width, height: png.ihdr.height, layout, stride: png.ihdr.width as usize * layout.channels(), samples };
+
 	Ok(png)
 }
 
-fn get_rgb_a(rgba: &mut Vec<u16>, rgb: &mut Vec<u16>, alpha: &mut Vec<u16>) -> Result<bool> {
+// ********************************************************
+// Grayscale Is Copied Into All Three RGB Samples
+// Images Without Alpha Are Filled As Fully Opaque
+// ********************************************************
+fn get_rgb_a(samples: &[u16], channels: usize, rgb: &mut Vec<u16>, alpha: &mut Vec<u16>) -> Result<bool> {
 	let mut i = 0;
-	while i < rgba.len() {
-		rgb.push(rgba[i]);
-		rgb.push(rgba[i + 1]);
-		rgb.push(rgba[i + 2]);
-		alpha.push(rgba[i + 3]);
-		i += 4;
+	while i < samples.len() {
+		if channels < 3 {
+			rgb.push(samples[i]);
+			rgb.push(samples[i]);
+			rgb.push(samples[i]);
+		} else {
+			rgb.push(samples[i]);
+			rgb.push(samples[i + 1]);
+			rgb.push(samples[i + 2]);
+		}
+		if channels == 2 || channels == 4 {
+			alpha.push(samples[i + channels - 1]);
+		} else {
+			alpha.push(0xFFFF);
+		}
+		i += channels;
 	}
 	Ok(true)
 }
 
+// IHDR Has Been Validated, Every Color Type Left Has A Layout
+fn get_layout(color_type: u8) -> ChannelLayout {
+	ChannelLayout::from_color_type(color_type).unwrap_or_default()
+}
 
-pub fn encode_png(mut png: PNG, depth: u8, result: &str) -> Result<bool> {
-	let filterd_rgba = match filter_rgba(&mut png, depth) {
-		Ok(filterd_rgba) => filterd_rgba,
-		Err(e) => return Err(e),
+// ********************************************************
+// Palette Entries Are Widened To 16bit (v * 257)
+// Rgb Without tRNS, Otherwise Rgba With Entries Past tRNS Fully Opaque
+// ********************************************************
+fn get_palette(ihdr: &PNG_IHDR, header: &[u8], idat_offset: u64) -> Result<(ChannelLayout, Vec<u16>)> {
+	let chunks = get_header_chunks(header);
+	let plte = match chunks.iter().find(|&&(chunk_type, _)| chunk_type == ffi::PLTE) {
+		Some(&(_, plte)) => plte,
+		None => return Err(Error::InvalidChunk { chunk: ffi::PLTE, offset: idat_offset, reason: "Missing PLTE" }),
 	};
-
-	let mut deflated = vec![];
-	match filterd_rgba.zlib_encode(Compression::Default).read_to_end(&mut deflated) {
-		Ok(_) => (),
-		Err(e) => return Err(e),
+	let plte_offset = HEADER_OFFSET + (plte.as_ptr() as usize - header.as_ptr() as usize) as u64;
+	let plte = get_chunk_data(plte);
+	if plte.is_empty() || !plte.len().is_multiple_of(3) || plte.len() / 3 > (1 << ihdr.depth) {
+		return Err(Error::InvalidChunk { chunk: ffi::PLTE, offset: plte_offset, reason: "Invalid Palette Size" });
+	}
+	let trns = match chunks.iter().find(|&&(chunk_type, _)| chunk_type == ffi::tRNS) {
+		Some(&(_, trns)) => get_chunk_data(trns),
+		None => &[],
 	};
 
-	let out = match File::create(result) {
-		Ok(out) => out,
-		Err(e) => return Err(e),
-	};
+	let layout = if trns.is_empty() { ChannelLayout::Rgb } else { ChannelLayout::Rgba };
+	let mut palette = Vec::<u16>::with_capacity(plte.len() / 3 * layout.channels());
+	for (index, entry) in plte.chunks(3).enumerate() {
+		palette.extend(entry.iter().map(|&v| v as u16 * 257));
+		if layout == ChannelLayout::Rgba {
+			palette.push(if index < trns.len() { trns[index] as u16 * 257 } else { 0xFFFF });
+		}
+	}
+	Ok((layout, palette))
+}
 
-	let mut writer = BufWriter::new(&out);
-	let mut writer_mut = writer.get_mut();
+// Indices Looked Up In The Entries get_palette Expanded
+fn get_palette_samples(raw: &[u16], layout: ChannelLayout, palette: &[u16], idat_offset: u64) -> Result<Vec<u16>> {
+	let channels = layout.channels();
+	let mut samples = Vec::<u16>::with_capacity(raw.len() * channels);
+	for &index in raw {
+		let start = index as usize * channels;
+		if start >= palette.len() {
+			return Err(Error::InvalidChunk { chunk: ffi::IDAT, offset: idat_offset, reason: "Invalid Palette Index" });
+		}
+		samples.extend_from_slice(&palette[start..start + channels]);
+	}
+	Ok(samples)
+}
 
-	match writer_mut.write_u64::<BigEndian>(ffi::PNG_SIG) {
-		Ok(_) => (),
+// ********************************************************
+// Walk The Chunks Copied By get_header: Length, Type, Data, CRC
+// Returns (Chunk Type, Whole Chunk)
+// ********************************************************
+fn get_header_chunks(header: &[u8]) -> Vec<(u32, &[u8])> {
+	let mut chunks = vec![];
+	let mut offset = 0;
+	while offset + 12 <= header.len() {
+		let length = BigEndian::read_u32(&header[offset..]) as usize;
+		let chunk_type = BigEndian::read_u32(&header[offset + 4..]);
+		if offset + 12 + length > header.len() {
+			break;
+		}
+		chunks.push((chunk_type, &header[offset..offset + 12 + length]));
+		offset += 12 + length;
+	}
+	chunks
+}
+
+fn get_chunk_data(chunk: &[u8]) -> &[u8] {
+	&chunk[8..chunk.len() - 4]
+}
+
+// Length + Type + Data + CRC32 (Type + Data)
+fn get_chunk(chunk_type: u32, data: &[u8]) -> Vec<u8> {
+	let mut chunk = vec![0; 8];
+	BigEndian::write_u32(&mut chunk, data.len() as u32);
+	BigEndian::write_u32(&mut chunk[4..], chunk_type);
+	chunk.extend_from_slice(data);
+	let crc = crc32::checksum_ieee(&chunk[4..]);
+	chunk.extend_from_slice(&[(crc >> 24) as u8, (crc >> 16) as u8, (crc >> 8) as u8, crc as u8]);
+	chunk
+}
+
+// Header Chunks Start After The Signature (8) And IHDR (25)
+const HEADER_OFFSET: u64 = 33;
+
+// ********************************************************
+// Tracks The Byte Offset For Error Reporting
+// ********************************************************
+struct PositionReader<'a> {
+	reader: Box<dyn Read + 'a>,
+	offset: u64,
+}
+
+impl<'a> Read for PositionReader<'a> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		let n = match self.reader.read(buf) {
+			Ok(n) => n,
+			Err(e) => return Err(e),
+		};
+		self.offset += n as u64;
+		Ok(n)
+	}
+}
+
+// ********************************************************
+// Chunk Type Property Bits: Bit 5 Of Each Byte
+// First Byte: Ancillary, Fourth Byte: Safe To Copy
+// ********************************************************
+pub fn is_critical(chunk_type: u32) -> bool {
+	chunk_type & 0x20000000 == 0
+}
+
+pub fn is_safe_to_copy(chunk_type: u32) -> bool {
+	chunk_type & 0x20 != 0
+}
+
+// ********************************************************
+// Chunks Copied Verbatim From PNG.header/PNG.trailer
+// Critical Chunks Never Depend On It, Typed Metadata Is Always Written
+// ********************************************************
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChunkPolicy {
+	KeepAll,
+	// Unknown Chunks Are Kept Only With The Safe-To-Copy Bit Set
+	SafeToCopy,
+	// Only These Chunk Types (ffi Constants)
+	Keep(Vec<u32>),
+	StripAll,
+}
+
+// Ancillary Chunks That Change How The Pixels Are Displayed
+pub const RENDERING_CHUNKS: [u32; 5] = [ffi::gAMA, ffi::sRGB, ffi::cHRM, ffi::iCCP, ffi::tRNS];
+
+// ********************************************************
+// strip: Some(keep) Drops Every Ancillary Chunk Not Listed In keep,
+// Typed Metadata Included, On Top Of The Chunk Policy
+// filter: How Each Scanline's Filter Is Chosen
+// level: zlib Compression Level 0-9 For The Image Data, 0 Stores It Uncompressed
+// ********************************************************
+#[derive(Debug, Clone)]
+pub struct EncodeOptions {
+	pub chunks: ChunkPolicy,
+	pub strip: Option<Vec<u32>>,
+	pub filter: FilterStrategy,
+	pub level: u8,
+	pub zlib_strategy: ZlibStrategy,
+}
+
+impl Default for EncodeOptions {
+	fn default() -> EncodeOptions {
+		EncodeOptions {
+			chunks: ChunkPolicy::SafeToCopy,
+			strip: None,
+			filter: FilterStrategy::default(),
+			level: 6,
+			zlib_strategy: ZlibStrategy::default(),
+		}
+	}
+}
+
+impl EncodeOptions {
+	// Smallest Output That Still Renders The Same
+	pub fn optimize() -> EncodeOptions {
+		EncodeOptions { chunks: ChunkPolicy::SafeToCopy, strip: Some(RENDERING_CHUNKS.to_vec()), ..Default::default() }
+	}
+
+	// Fastest Output For Intermediate Files: Unfiltered, Stored Without Compression
+	pub fn store() -> EncodeOptions {
+		EncodeOptions { filter: FilterStrategy::Fixed(FilterType::None), level: 0, ..Default::default() }
+	}
+}
+
+// ********************************************************
+// PLTE, tRNS And hIST Depend On The Color Type And Bit Depth,
+// Drop Them When Either Changes On Encode. Every Other Chunk Is
+// Unknown Here: The Encoder Cannot Tell Whether The Pixels Were Edited,
+// So Unsafe-To-Copy Ones Only Survive ChunkPolicy::KeepAll
+// ********************************************************
+fn get_copied_chunks(blob: &[u8], png: &PNG, depth: u8, color_type: u8, options: &EncodeOptions) -> Vec<u8> {
+	let unchanged = depth == png.ihdr.depth && color_type == png.ihdr.color_type;
+	let mut chunks = Vec::<u8>::new();
+	for (chunk_type, chunk) in get_header_chunks(blob) {
+		let keep = match chunk_type {
+			ffi::PLTE | ffi::tRNS | ffi::hIST if !unchanged => false,
+			_ if is_critical(chunk_type) => true,
+			_ if options.strip.as_ref().is_some_and(|keep| !keep.contains(&chunk_type)) => false,
+			_ => match options.chunks {
+				ChunkPolicy::KeepAll => true,
+				ChunkPolicy::SafeToCopy => is_safe_to_copy(chunk_type) || [ffi::tRNS, ffi::hIST].contains(&chunk_type),
+				ChunkPolicy::Keep(ref types) => types.contains(&chunk_type),
+				ChunkPolicy::StripAll => false,
+			},
+		};
+		if keep {
+			chunks.extend_from_slice(chunk);
+		}
+	}
+	chunks
+}
+
+fn get_encode_metadata<'a>(png: &'a PNG, options: &EncodeOptions) -> Cow<'a, Metadata> {
+	match options.strip {
+		Some(ref keep) => Cow::Owned(metadata::get_stripped_metadata(&png.metadata, keep)),
+		None => Cow::Borrowed(&png.metadata),
+	}
+}
+
+// Metadata Chunks Are Regenerated Around The Header Blob In Spec Order
+fn get_encode_header(png: &PNG, depth: u8, color_type: u8, options: &EncodeOptions) -> Result<Vec<u8>> {
+	let metadata = get_encode_metadata(png, options);
+	let mut header = match metadata::get_pre_plte_chunks(&metadata, depth, color_type) {
+		Ok(header) => header,
 		Err(e) => return Err(e),
 	};
-	match writer_mut.write_u32::<BigEndian>(png.ihdr.total_bytes) {
-		Ok(_) => (),
+	header.extend_from_slice(&get_copied_chunks(&png.header, png, depth, color_type, options));
+	match metadata::get_post_plte_chunks(&metadata, depth, color_type) {
+		Ok(chunks) => header.extend_from_slice(&chunks),
 		Err(e) => return Err(e),
 	};
-	match writer_mut.write_u32::<BigEndian>(ffi::IHDR) {
-		Ok(_) => (),
+	Ok(header)
+}
+
+// tIME And Text That Followed IDAT, Interleaved With The Trailer Blob In File Order
+fn get_encode_trailer(png: &PNG, depth: u8, color_type: u8, options: &EncodeOptions) -> Result<Vec<u8>> {
+	let trailer = get_copied_chunks(&png.trailer, png, depth, color_type, options);
+	metadata::get_after_idat_chunks(&get_encode_metadata(png, options), &trailer)
+}
+
+// Header And Trailer Bytes encode_png Would Write
+fn get_encode_chunks_size(png: &PNG, depth: u8, color_type: u8, options: &EncodeOptions) -> Result<usize> {
+	match (get_encode_header(png, depth, color_type, options), get_encode_trailer(png, depth, color_type, options)) {
+		(Ok(header), Ok(trailer)) => Ok(header.len() + trailer.len()),
+		(Err(e), _) | (_, Err(e)) => Err(e),
+	}
+}
+
+// Critical Chunks And Those Listed In keep, Whatever The Output Format
+fn get_kept_chunks(blob: &[u8], keep: &[u32]) -> Vec<u8> {
+	let mut chunks = Vec::<u8>::new();
+	for (chunk_type, chunk) in get_header_chunks(blob) {
+		if is_critical(chunk_type) || keep.contains(&chunk_type) {
+			chunks.extend_from_slice(chunk);
+		}
+	}
+	chunks
+}
+
+// ********************************************************
+// Removes Every Ancillary Chunk Not Listed In keep From png,
+// Returns The Number Of Chunk Bytes Saved When Encoding With options
+// ********************************************************
+pub fn strip_metadata(png: &mut PNG, keep: &[u32], options: &EncodeOptions) -> Result<usize> {
+	// Sized As encode_png Would Write It At The Source Depth
+	let depth = if png.ihdr.depth == DEPTH_16 { DEPTH_16 } else { DEPTH_8 };
+	let color_type = get_encode_color_type(png);
+
+	let before = match get_encode_chunks_size(png, depth, color_type, options) {
+		Ok(before) => before,
 		Err(e) => return Err(e),
 	};
+	// Whether PLTE, tRNS And hIST Fit The Output Depth And Color Type Is Decided At Encode
+	png.header = get_kept_chunks(&png.header, keep);
+	png.trailer = get_kept_chunks(&png.trailer, keep);
+	png.metadata = metadata::get_stripped_metadata(&png.metadata, keep);
+	match get_encode_chunks_size(png, depth, color_type, options) {
+		Ok(after) => Ok(before - after),
+		Err(e) => Err(e),
+	}
+}
 
-	let mut ihdr = Vec::<u8>::new();
+fn get_channels(color_type: u8) -> usize {
+	match color_type {
+		COLOR_TYPE_GRAY | COLOR_TYPE_PALETTE => 1,
+		COLOR_TYPE_GRAY_ALPHA => 2,
+		COLOR_TYPE_RGB => 3,
+		_ => 4,
+	}
+}
 
-	// CRC32 Requires Chunk Tag
-	ihdr.push(0x49);
-	ihdr.push(0x48);
-	ihdr.push(0x44);
-	ihdr.push(0x52);
+// Filters Operate On Whole Bytes, Sub Byte Depths Use 1
+fn get_bytes_per_pixel(channels: usize, depth: u8) -> usize {
+	std::cmp::max(1, channels * depth as usize / 8)
+}
 
-	for i in (0..4).rev() {
-		ihdr.push((png.ihdr.width >> i * 8) as u8);
-	}
-	for i in (0..4).rev() {
-		ihdr.push((png.ihdr.height >> i * 8) as u8);
+// Scanlines Are Padded To A Whole Byte
+fn get_row_bytes(width: u32, channels: usize, depth: u8) -> usize {
+	(width as usize * channels * depth as usize).div_ceil(8)
+}
+
+// *****************************************************************
+// Alpha Is Dropped Whenever It Is Entirely Opaque, The Requested
+// Grayscale Is Only Written When Nothing Would Be Lost (R == G == B)
+// *****************************************************************
+fn get_encode_color_type(png: &PNG) -> u8 {
+	let image = &png.image;
+	let alpha = image.channels() - 1;
+	let keep_alpha = image.layout.has_alpha() && (0..image.height).any(|y| {
+		image.row(y).chunks(image.channels()).any(|p| p[alpha] != 0xFFFF)
+	});
+	let gray = png.ihdr.color_type & 0x02 == 0 && (image.layout.is_gray() || (0..image.height).all(|y| {
+		image.row(y).chunks(image.channels()).all(|p| p[0] == p[1] && p[1] == p[2])
+	}));
+
+	match (gray, keep_alpha) {
+		(true, false) => COLOR_TYPE_GRAY,
+		(true, true) => COLOR_TYPE_GRAY_ALPHA,
+		(false, false) => COLOR_TYPE_RGB,
+		(false, true) => COLOR_TYPE_RGBA,
 	}
+}
 
-	ihdr.push(depth);
-	ihdr.push(png.ihdr.color_type);
-	ihdr.push(png.ihdr.compression);
-	ihdr.push(png.ihdr.filter);
-	ihdr.push(png.ihdr.interlace);
+// ********************************************************
+// PNG.image Must Cover ihdr.width x ihdr.height Before Anything Is Written
+// ********************************************************
+fn check_image(png: &PNG) -> Result<bool> {
+	let image = &png.image;
+	let row_samples = image.width as usize * image.channels();
+	if image.width != png.ihdr.width || image.height != png.ihdr.height || image.width == 0 || image.height == 0 {
+		return Err(Error::InvalidImage { reason: "Image Size Does Not Match IHDR" });
+	}
+	if image.stride < row_samples || image.samples.len() < image.stride * (image.height as usize - 1) + row_samples {
+		return Err(Error::InvalidImage { reason: "Image Buffer Too Small" });
+	}
+	Ok(true)
+}
 
-	let ihdr_crc32 = crc32::checksum_ieee(ihdr.as_slice());
+// Compression And Filter Method Are Copied, Both Are Always 0
+fn get_encode_ihdr(ihdr: &PNG_IHDR, depth: u8, color_type: u8, interlace: u8) -> Vec<u8> {
+	let mut data = vec![0; 8];
+	BigEndian::write_u32(&mut data, ihdr.width);
+	BigEndian::write_u32(&mut data[4..], ihdr.height);
+	data.extend_from_slice(&[depth, color_type, ihdr.compression, ihdr.filter, interlace]);
+	get_chunk(ffi::IHDR, &data)
+}
 
-	match writer_mut.write_u32::<BigEndian>(png.ihdr.width) {
-		Ok(_) => (),
-		Err(e) => return Err(e),
+pub fn encode_png(png: PNG, depth: u8, result: &str) -> Result<bool> {
+	let out = match File::create(result) {
+		Ok(out) => out,
+		Err(e) => return Err(Error::from(e)),
 	};
-	match writer_mut.write_u32::<BigEndian>(png.ihdr.height) {
+
+	encode_png_to_writer(&png, depth, BufWriter::new(out))
+}
+
+// ********************************************************
+// Writes Strictly In Order, Works With Any Sink (Vec<u8>, Sockets, ...)
+// ********************************************************
+pub fn encode_png_to_writer<W: Write>(png: &PNG, depth: u8, writer: W) -> Result<bool> {
+	encode_png_with_options(png, depth, writer, &EncodeOptions::default())
+}
+
+pub fn encode_png_with_options<W: Write>(png: &PNG, depth: u8, mut writer: W, options: &EncodeOptions) -> Result<bool> {
+	// Only 8bit And 16bit Output Is Written
+	if depth != DEPTH_8 && depth != DEPTH_16 {
+		return Err(Error::Unsupported { chunk: ffi::IHDR, offset: 24, feature: "Bit Depth" });
+	}
+	match check_image(png) {
 		Ok(_) => (),
 		Err(e) => return Err(e),
 	};
-	match writer_mut.write_u8(depth) {
-		Ok(_) => (),
+	let color_type = get_encode_color_type(png);
+	// Any Non Zero Interlace Method Is Written As Adam7
+	let interlace = std::cmp::min(png.ihdr.interlace, 1);
+	let filterd_rgba = match filter_rgba(png, depth, color_type, options.filter) {
+		Ok(filterd_rgba) => filterd_rgba,
 		Err(e) => return Err(e),
 	};
-	match writer_mut.write_u8(png.ihdr.color_type) {
-		Ok(_) => (),
+	let header = match get_encode_header(png, depth, color_type, options) {
+		Ok(header) => header,
 		Err(e) => return Err(e),
 	};
-	match writer_mut.write_u8(png.ihdr.compression) {
-		Ok(_) => (),
+	let trailer = match get_encode_trailer(png, depth, color_type, options) {
+		Ok(trailer) => trailer,
 		Err(e) => return Err(e),
 	};
-	match writer_mut.write_u8(png.ihdr.filter) {
-		Ok(_) => (),
+
+	let deflated = match zlib::get_deflated(&filterd_rgba, options.level, options.zlib_strategy) {
+		Ok(deflated) => deflated,
 		Err(e) => return Err(e),
 	};
-	match writer_mut.write_u8(png.ihdr.interlace) {
+
+	match writer.write_u64::<BigEndian>(ffi::PNG_SIG) {
 		Ok(_) => (),
-		Err(e) => return Err(e),
+		Err(e) => return Err(Error::from(e)),
 	};
-	match writer_mut.write_u32::<BigEndian>(ihdr_crc32) {
+	match writer.write_all(&get_encode_ihdr(&png.ihdr, depth, color_type, interlace)) {
 		Ok(_) => (),
-		Err(e) => return Err(e),
+		Err(e) => return Err(Error::from(e)),
 	};
-	match writer_mut.write(&png.header) {
+	match writer.write_all(&header) {
 		Ok(_) => (),
-		Err(e) => return Err(e),
+		Err(e) => return Err(Error::from(e)),
 	};
 
 	for c in deflated.chunks(ffi::MAX_IDAT_SIZE) {
-		match writer_mut.write_u32::<BigEndian>(c.len() as u32) {
+		match writer.write_all(&get_chunk(ffi::IDAT, c)) {
 			Ok(_) => (),
-			Err(e) => return Err(e),
-		};
-		let mut crc_check = Vec::<u8>::new();
-
-		crc_check.push(0x49);
-		crc_check.push(0x44);
-		crc_check.push(0x41);
-		crc_check.push(0x54);
-
-		match crc_check.write(c) {
-			Ok(_) => (),
-			Err(e) => return Err(e),
-		};
-		let crc = crc32::checksum_ieee(crc_check.as_slice());
-
-		match writer_mut.write(&crc_check) {
-			Ok(_) => (),
-			Err(e) => return Err(e),
-		};
-		match writer_mut.write_u32::<BigEndian>(crc) {
-			Ok(_) => (),
-			Err(e) => return Err(e),
+			Err(e) => return Err(Error::from(e)),
 		};
 	}
 
-	match writer_mut.write_u64::<BigEndian>(ffi::IEND) {
+	match writer.write_all(&trailer) {
 		Ok(_) => (),
-		Err(e) => return Err(e),
+		Err(e) => return Err(Error::from(e)),
 	};
-	match writer_mut.write_u32::<BigEndian>(ffi::TAIL) {
+
+	match writer.write_u64::<BigEndian>(ffi::IEND) {
 		Ok(_) => (),
-		Err(e) => return Err(e),
+		Err(e) => return Err(Error::from(e)),
+	};
+	match writer.write_u32::<BigEndian>(ffi::TAIL) {
+		Ok(_) => (),
+		Err(e) => return Err(Error::from(e)),
+	};
+	match writer.flush() {
+		Ok(_) => (),
+		Err(e) => return Err(Error::from(e)),
 	};
 
 	Ok(true)
 }
 
-fn parse_ihdr(reader: &mut SeekableReader) -> Result<PNG_IHDR> {
+fn parse_ihdr(reader: &mut PositionReader, options: &DecodeOptions, warnings: &mut Vec<Error>) -> Result<PNG_IHDR> {
 	let mut header = PNG_IHDR { ..Default::default() };
 
-	let png_sig = match reader.read_u64::<BigEndian>() {
+	match reader.read_u64::<BigEndian>() {
 		Err(e) => {
-			return Err(e);
+			return Err(Error::from_io(e, 0, 0));
 		},
 		Ok(png_sig) => {
 			if png_sig != ffi::PNG_SIG {
-				return Err(Error::new(ErrorKind::InvalidData, "Invalid PNG Signature"));
+				return Err(Error::InvalidSignature { offset: 0 });
 			}
-			png_sig
 		},
 	};
 
 	header.total_bytes = match reader.read_u32::<BigEndian>() {
 		Err(e) => {
-			return Err(e);
+			return Err(Error::from_io(e, ffi::IHDR, 8));
 		},
 		Ok(total_bytes) => {
 			if total_bytes != 13 {
-				return Err(Error::new(ErrorKind::InvalidData, "Invalid Byte Count"));
+				return Err(Error::InvalidHeader { chunk: ffi::IHDR, offset: 8, field: "Length" });
 			}
 			total_bytes
 		},
@@ -277,24 +784,26 @@ fn parse_ihdr(reader: &mut SeekableReader) -> Result<PNG_IHDR> {
 
 	match reader.read_u32::<BigEndian>() {
 		Err(e) => {
-			return Err(e);
+			return Err(Error::from_io(e, ffi::IHDR, 12));
 		},
 		Ok(ihdr) => {
 			if ihdr != ffi::IHDR {
-				return Err(Error::new(ErrorKind::InvalidData, "Invalid IHDR"));
+				return Err(Error::InvalidHeader { chunk: ihdr, offset: 12, field: "Chunk Type" });
 			}
-			()
 		},
 	};
 
-	// Limit Image Size To 65535x65535.
+	// Image Size Is Capped By DecodeLimits, Not Just The Spec
 	header.width = match reader.read_u32::<BigEndian>() {
 		Err(e) => {
-			return Err(e);
+			return Err(Error::from_io(e, ffi::IHDR, 16));
 		},
 		Ok(width) => {
-			if width <= 0 || width >= (1 << 16) - 1 {
-				return Err(Error::new(ErrorKind::InvalidData, "Invalid Width"));
+			if width == 0 || width > MAX_DIMENSION {
+				return Err(Error::InvalidHeader { chunk: ffi::IHDR, offset: 16, field: "Width" });
+			}
+			if width > options.limits.max_width {
+				return Err(Error::LimitExceeded { chunk: ffi::IHDR, offset: 16, limit: "Width" });
 			}
 			width
 		},
@@ -302,35 +811,39 @@ fn parse_ihdr(reader: &mut SeekableReader) -> Result<PNG_IHDR> {
 
 	header.height = match reader.read_u32::<BigEndian>() {
 		Err(e) => {
-			return Err(e);
+			return Err(Error::from_io(e, ffi::IHDR, 20));
 		},
 		Ok(height) => {
-			if height <= 0 || height >= (1 << 16) - 1 {
-				return Err(Error::new(ErrorKind::InvalidData, "Invalid Height"));
+			if height == 0 || height > MAX_DIMENSION {
+				return Err(Error::InvalidHeader { chunk: ffi::IHDR, offset: 20, field: "Height" });
+			}
+			if height > options.limits.max_height {
+				return Err(Error::LimitExceeded { chunk: ffi::IHDR, offset: 20, limit: "Height" });
 			}
 			height
 		},
 	};
-	// Currently Only 16bit decoding supported, 8bit coming soon(TM)
+	// 16bit And 8bit Decoding Supported, 8bit Samples Are Widened To 16bit
 	header.depth = match reader.read_u8() {
 		Err(e) => {
-			return Err(e);
-		},
-		Ok(depth) => {
-			if depth != 8 && depth != 16 {
-				return Err(Error::new(ErrorKind::InvalidData, "Invalid Bit Depth"));
-			}
-			depth
+			return Err(Error::from_io(e, ffi::IHDR, 24));
 		},
+		Ok(depth) => depth,
 	};
-	// TrueColor With Alpha Only, Truecolor and Grayscale coming soon(TM)
+	// Grayscale Allows 1, 2, 4, 8 And 16bit, Palette 1, 2, 4 And 8bit, Everything Else 8 And 16bit
 	header.color_type = match reader.read_u8() {
 		Err(e) => {
-			return Err(e);
+			return Err(Error::from_io(e, ffi::IHDR, 25));
 		},
 		Ok(color_type) => {
-			if color_type != 6 {
-				return Err(Error::new(ErrorKind::InvalidData, "Invalid Color Type"));
+			let valid_depth = match color_type {
+				COLOR_TYPE_GRAY => [1, 2, 4, 8, 16].contains(&header.depth),
+				COLOR_TYPE_PALETTE => [1, 2, 4, 8].contains(&header.depth),
+				COLOR_TYPE_RGB | COLOR_TYPE_GRAY_ALPHA | COLOR_TYPE_RGBA => header.depth == DEPTH_8 || header.depth == DEPTH_16,
+				_ => return Err(Error::InvalidHeader { chunk: ffi::IHDR, offset: 25, field: "Color Type" }),
+			};
+			if !valid_depth {
+				return Err(Error::InvalidHeader { chunk: ffi::IHDR, offset: 24, field: "Bit Depth" });
 			}
 			color_type
 		},
@@ -338,11 +851,11 @@ fn parse_ihdr(reader: &mut SeekableReader) -> Result<PNG_IHDR> {
 	// PNG Only Supports Compression Type 0
 	header.compression = match reader.read_u8() {
 		Err(e) => {
-			return Err(e);
+			return Err(Error::from_io(e, ffi::IHDR, 26));
 		},
 		Ok(compression) => {
 			if compression != 0 {
-				return Err(Error::new(ErrorKind::InvalidData, "Invalid Compression Type"));
+				return Err(Error::InvalidHeader { chunk: ffi::IHDR, offset: 26, field: "Compression Method" });
 			}
 			compression
 		},
@@ -350,11 +863,11 @@ fn parse_ihdr(reader: &mut SeekableReader) -> Result<PNG_IHDR> {
 	// PNG Only Supports Filter Type 0 [None, Sub, Up, Avg Paeth]
 	header.filter = match reader.read_u8() {
 		Err(e) => {
-			return Err(e);
+			return Err(Error::from_io(e, ffi::IHDR, 27));
 		},
 		Ok(filter) => {
 			if filter != 0 {
-				return Err(Error::new(ErrorKind::InvalidData, "Invalid Filter Mode"));
+				return Err(Error::InvalidHeader { chunk: ffi::IHDR, offset: 27, field: "Filter Method" });
 			}
 			filter
 		},
@@ -362,506 +875,457 @@ fn parse_ihdr(reader: &mut SeekableReader) -> Result<PNG_IHDR> {
 
 	header.interlace = match reader.read_u8() {
 		Err(e) => {
-			return Err(e);
+			return Err(Error::from_io(e, ffi::IHDR, 28));
 		},
 		Ok(interlace) => {
-			if interlace != 0 {
-				return Err(Error::new(ErrorKind::InvalidData, "Interlace Not Supported"));
+			// 0: None, 1: Adam7
+			if interlace > 1 {
+				return Err(Error::InvalidHeader { chunk: ffi::IHDR, offset: 28, field: "Interlace Method" });
 			}
 			interlace
 		},
 	};
-	// TODO: Run CRC32 Check
 	header.crc = match reader.read_u32::<BigEndian>() {
 		Ok(crc) => crc,
+		Err(e) => return Err(Error::from_io(e, ffi::IHDR, 29)),
+	};
+
+	let mut ihdr = Vec::<u8>::new();
+	for i in (0..4).rev() {
+		ihdr.push((header.width >> (i * 8)) as u8);
+	}
+	for i in (0..4).rev() {
+		ihdr.push((header.height >> (i * 8)) as u8);
+	}
+	ihdr.extend_from_slice(&[header.depth, header.color_type, header.compression, header.filter, header.interlace]);
+
+	match check_crc(ffi::IHDR, &ihdr, header.crc, 8, options, warnings) {
+		Ok(_) => (),
 		Err(e) => return Err(e),
 	};
 
 	Ok(header)
 }
 
+// ********************************************************
+// Inflated Size Of The Filtered Scanlines, Including Every Adam7 Pass
+// ********************************************************
+fn get_image_bytes(ihdr: &PNG_IHDR) -> u64 {
+	let channels = get_channels(ihdr.color_type) as u64;
+	let row_bytes = |width: u32| (width as u64 * channels * ihdr.depth as u64).div_ceil(8);
+	if ihdr.interlace == 0 {
+		return (row_bytes(ihdr.width) + 1) * ihdr.height as u64;
+	}
+
+	let mut image_bytes = 0;
+	for &pass in ADAM7.iter() {
+		let (pass_width, pass_height) = get_pass_size(ihdr.width, ihdr.height, pass);
+		if pass_width > 0 && pass_height > 0 {
+			image_bytes += (row_bytes(pass_width) + 1) * pass_height as u64;
+		}
+	}
+	image_bytes
+}
+
+// ********************************************************
+// Peak Allocation Upper Bound: Inflated + Defiltered Scanlines, Raw + Widened Samples,
+// Palette Expansion To Rgba
+// The Compressed IDAT Buffer Gets What Is Left Of max_bytes, See get_idat
+// ********************************************************
+fn get_decode_bytes(ihdr: &PNG_IHDR) -> u64 {
+	let pixels = ihdr.width as u64 * ihdr.height as u64;
+	let samples = pixels * get_channels(ihdr.color_type) as u64;
+	2 * get_image_bytes(ihdr) + 4 * samples + 8 * pixels
+}
+
+fn check_limits(ihdr: &PNG_IHDR, limits: &DecodeLimits) -> Result<bool> {
+	let pixels = ihdr.width as u64 * ihdr.height as u64;
+	if pixels > limits.max_pixels {
+		return Err(Error::LimitExceeded { chunk: ffi::IHDR, offset: 16, limit: "Pixels" });
+	}
+
+	if get_decode_bytes(ihdr) > limits.max_bytes {
+		return Err(Error::LimitExceeded { chunk: ffi::IHDR, offset: 16, limit: "Bytes Allocated" });
+	}
+	Ok(true)
+}
+
+// ********************************************************
+// CRC32 Covers Chunk Type + Chunk Data
+// ********************************************************
+fn check_crc(chunk_type: u32, data: &[u8], crc: u32, offset: u64, options: &DecodeOptions, warnings: &mut Vec<Error>) -> Result<bool> {
+	if options.crc_check == CrcCheck::Ignore {
+		return Ok(true);
+	}
+
+	let mut tag = [0; 4];
+	BigEndian::write_u32(&mut tag, chunk_type);
+	let mut digest = crc32::Digest::new(crc32::IEEE);
+	digest.write(&tag);
+	digest.write(data);
+	check_crc_sum(chunk_type, crc, digest.sum32(), offset, options, warnings)
+}
+
+// found: CRC32 Computed Over The Chunk As It Was Read
+fn check_crc_sum(chunk_type: u32, crc: u32, found: u32, offset: u64, options: &DecodeOptions, warnings: &mut Vec<Error>) -> Result<bool> {
+	if options.crc_check == CrcCheck::Ignore || found == crc {
+		return Ok(true);
+	}
+
+	let error = Error::CrcMismatch { chunk: chunk_type, offset, expected: crc, found };
+	match options.crc_check {
+		CrcCheck::Strict => Err(error),
+		_ => {
+			warnings.push(error);
+			Ok(false)
+		},
+	}
+}
+
 // ********************************************************
 // Copy all bytes after IHDR before IDAT
 // Header buffer will be copied to encoding side unchanged
+// Returns The Length + Type Of The First IDAT Chunk Too
 // ********************************************************
-fn get_header(img: &mut SeekableReader) -> Result<Vec<u8>> {
+fn get_header(img: &mut PositionReader, options: &DecodeOptions, warnings: &mut Vec<Error>) -> Result<(Vec<u8>, u64)> {
 	let mut header = Vec::<u8>::new();
 	loop {
+		let offset = img.offset;
 		let chunk_tag = match img.read_u64::<BigEndian>() {
 			Ok(chunk_tag) => chunk_tag,
-			Err(e) => return Err(e),
+			Err(e) => return Err(Error::from_io(e, 0, offset)),
 		};
 
 		if (chunk_tag as u32) == ffi::IDAT {
-			match img.seek(SeekFrom::Current(-8)) {
-				Ok(_) => (),
-				Err(e) => return Err(e),
-			};
-			break;
+			return Ok((header, chunk_tag));
 		}
-
-		for i in (0..8).rev() {
-			header.push((chunk_tag >> 8 * i) as u8);
+		// Bit 5 Of The First Byte Clear: Critical, Only PLTE May Precede IDAT
+		if is_critical(chunk_tag as u32) && (chunk_tag as u32) != ffi::PLTE {
+			return Err(Error::Unsupported { chunk: chunk_tag as u32, offset, feature: "Critical Chunk" });
 		}
 
-		match img.take(chunk_tag >> 32).read_to_end(&mut header) {
+		match get_chunk_into(img, chunk_tag, offset, 0, &mut header, options, warnings) {
 			Ok(_) => (),
 			Err(e) => return Err(e),
 		};
+	}
+}
 
-		match img.take(4).read_to_end(&mut header) {
+// ********************************************************
+// Reads Everything After The Last IDAT Up To And Including IEND
+// ********************************************************
+fn get_trailer(img: &mut PositionReader, chunk_tag: u64, header_bytes: u64, options: &DecodeOptions, warnings: &mut Vec<Error>) -> Result<Vec<u8>> {
+	let mut trailer = Vec::<u8>::new();
+	let mut chunk_tag = chunk_tag;
+	loop {
+		let offset = img.offset - 8;
+		// IEND Is Empty, Only Its CRC Follows
+		if chunk_tag == ffi::IEND {
+			match img.read_u32::<BigEndian>() {
+				Ok(crc) => match check_crc(ffi::IEND as u32, &[], crc, offset, options, warnings) {
+					Ok(_) => return Ok(trailer),
+					Err(e) => return Err(e),
+				},
+				Err(e) => return Err(Error::from_io(e, ffi::IEND as u32, offset)),
+			};
+		}
+		if chunk_tag as u32 == ffi::IDAT {
+			return Err(Error::InvalidChunk { chunk: ffi::IDAT, offset, reason: "IDAT Chunks Are Not Consecutive" });
+		}
+		if is_critical(chunk_tag as u32) {
+			return Err(Error::Unsupported { chunk: chunk_tag as u32, offset, feature: "Critical Chunk" });
+		}
+
+		match get_chunk_into(img, chunk_tag, offset, header_bytes, &mut trailer, options, warnings) {
 			Ok(_) => (),
 			Err(e) => return Err(e),
 		};
+
+		chunk_tag = match img.read_u64::<BigEndian>() {
+			Ok(chunk_tag) => chunk_tag,
+			Err(e) => return Err(Error::from_io(e, 0, img.offset)),
+		};
 	}
-	Ok(header)
 }
 
-fn get_rgba(img: &mut SeekableReader, width: u32, depth: u8) -> Result<Vec<u16>> {
+// ********************************************************
+// Appends One Whole Chunk (Length, Type, Data, CRC) To blob
+// The Tag Has Already Been Read, metadata_bytes Counts Chunks Kept Elsewhere
+// ********************************************************
+fn get_chunk_into(img: &mut PositionReader, chunk_tag: u64, offset: u64, metadata_bytes: u64, blob: &mut Vec<u8>, options: &DecodeOptions, warnings: &mut Vec<Error>) -> Result<bool> {
+	if metadata_bytes + blob.len() as u64 + 12 + (chunk_tag >> 32) > options.limits.max_metadata_bytes {
+		return Err(Error::LimitExceeded { chunk: chunk_tag as u32, offset, limit: "Metadata Bytes" });
+	}
+
+	let start = blob.len();
+	for i in (0..8).rev() {
+		blob.push((chunk_tag >> (8 * i)) as u8);
+	}
+
+	match img.take(chunk_tag >> 32).read_to_end(blob) {
+		Ok(_) => (),
+		Err(e) => return Err(Error::from_io(e, chunk_tag as u32, offset)),
+	};
+
+	match img.take(4).read_to_end(blob) {
+		Ok(_) => (),
+		Err(e) => return Err(Error::from_io(e, chunk_tag as u32, offset)),
+	};
+
+	if blob.len() != start + 12 + (chunk_tag >> 32) as usize {
+		return Err(Error::Truncated { chunk: chunk_tag as u32, offset });
+	}
+	let crc = BigEndian::read_u32(&blob[blob.len() - 4..]);
+	check_crc(chunk_tag as u32, &blob[start + 8..blob.len() - 4], crc, offset, options, warnings)
+}
+
+// ********************************************************
+// Returns Unscaled Samples In Image Order, Interlaced Or Not
+// The Tag Of The Chunk Following The Last IDAT Is Returned Alongside
+// ********************************************************
+fn get_idat(img: &mut PositionReader, ihdr: &PNG_IHDR, idat_tag: u64, options: &DecodeOptions, warnings: &mut Vec<Error>) -> Result<(Vec<u16>, u64)> {
 	let mut data_chunk = vec![];
+	let mut idat_header = idat_tag;
+	// Offset Of The First IDAT, Errors In The Image Data Point Here
+	let idat_offset = img.offset - 8;
+	// Compressed Bytes Share max_bytes With The Buffers Decoding Allocates
+	let idat_budget = options.limits.max_bytes.saturating_sub(get_decode_bytes(ihdr));
 	// Collect All IDAT Bytes
-	loop {
-		let idat_header = match img.read_u64::<BigEndian>() {
-			Ok(idat_header) => idat_header,
-			Err(e) => return Err(e),
-		};
-		if idat_header as u32 != ffi::IDAT {
-			match img.seek(SeekFrom::Current(-8)) {
-				Ok(_) => (),
-				Err(e) => return Err(e),
-			}
-			break;
+	while idat_header as u32 == ffi::IDAT {
+		let offset = img.offset - 8;
+		if data_chunk.len() as u64 + (idat_header >> 32) > idat_budget {
+			return Err(Error::LimitExceeded { chunk: ffi::IDAT, offset, limit: "Bytes Allocated" });
 		}
+		let start = data_chunk.len();
 		match img.take(idat_header >> 32).read_to_end(&mut data_chunk) {
 			Ok(_) => (),
-			Err(e) => return Err(e),
+			Err(e) => return Err(Error::from_io(e, ffi::IDAT, offset)),
 		};
+		if data_chunk.len() != start + (idat_header >> 32) as usize {
+			return Err(Error::Truncated { chunk: ffi::IDAT, offset });
+		}
 
-		let idat_crc = match img.read_u32::<BigEndian>() {
-			Ok(idat_crc) => idat_crc,
-			Err(e) => return Err(e),
+		match img.read_u32::<BigEndian>() {
+			Ok(crc) => match check_crc(ffi::IDAT, &data_chunk[start..], crc, offset, options, warnings) {
+				Ok(_) => (),
+				Err(e) => return Err(e),
+			},
+			Err(e) => return Err(Error::from_io(e, ffi::IDAT, offset)),
+		};
+
+		idat_header = match img.read_u64::<BigEndian>() {
+			Ok(idat_header) => idat_header,
+			Err(e) => return Err(Error::from_io(e, 0, img.offset)),
 		};
 	}
-	// Inflate Compressed IDAT Bytes
+	// Inflate Compressed IDAT Bytes, Never Past The Size IHDR Allows
+	let image_bytes = get_image_bytes(ihdr);
 	let mut inflated = vec![];
-	match data_chunk.zlib_decode().read_to_end(&mut inflated) {
+	match data_chunk.zlib_decode().take(image_bytes).read_to_end(&mut inflated) {
 		Ok(_) => {},
-		Err(e) => return Err(e),
+		Err(e) => return Err(Error::Zlib { chunk: ffi::IDAT, offset: idat_offset, message: e.to_string() }),
 	};
 
-	let rgba = match get_unfilterd_idat(&mut inflated, width, depth) {
-		Ok(rgba) => rgba,
-		Err(e) => return Err(e),
-	};
+	let channels = get_channels(ihdr.color_type);
+	if ihdr.interlace == 0 {
+		if (inflated.len() as u64) < image_bytes {
+			return Err(Error::Truncated { chunk: ffi::IDAT, offset: idat_offset });
+		}
+		let decode = match get_unfilterd_idat(&inflated, ihdr.width, ihdr.depth, channels, idat_offset) {
+			Ok(decode) => decode,
+			Err(e) => return Err(e),
+		};
+		return Ok((get_raw_samples(&decode, ihdr.width, ihdr.depth, channels), idat_header));
+	}
 
-	Ok(rgba)
+	match get_deinterlaced(&inflated, ihdr, channels, idat_offset) {
+		Ok(raw) => Ok((raw, idat_header)),
+		Err(e) => Err(e),
+	}
 }
 
 // ************************************************************************************
-// x=the byte being filtered;
-// a=the byte corresponding to x in the pixel immediately before the pixel containing x
-// b=the byte corresponding to x in the previous scanline;
-// c=the byte corresponding to b in the pixel immediately before the pixel containing b
+// Adam7 Passes (x0, y0, dx, dy): Seven Reduced Images, Each Filtered On Its Own
 // ************************************************************************************
-fn get_unfilterd_idat(inflated: &mut Vec<u8>, width: u32, depth: u8) -> Result<Vec<u16>> {
-	let mut decode = vec![];
-	for c in inflated.chunks(((width * depth as u32) / 2 + 1) as usize) {
-		match c[0] {
-			0x00 => none_defilter(c, &mut decode),
-			0x01 => sub_defilter(c, depth, &mut decode),
-			0x02 => up_defilter(c, width, depth, &mut decode),
-			0x03 => avg_defilter(c, width, depth, &mut decode),
-			0x04 => paeth_defilter(c, width, depth, &mut decode),
-			_ => return Err(Error::new(ErrorKind::InvalidData, "Invalid Filter Type")),
-		}
-	}
+const ADAM7: [(u32, u32, u32, u32); 7] = [(0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4), (0, 2, 2, 4), (1, 0, 2, 2), (0, 1, 1, 2)];
 
-	// Conver to u16
-	let mut rgba = Vec::<u16>::new();
-	let mut count = 0;
-	while count < decode.len() {
-		rgba.push(((decode[count] as u16) << 8) + decode[count + 1] as u16);
-		count += 2;
-	}
-	Ok(rgba)
+fn get_pass_size(width: u32, height: u32, pass: (u32, u32, u32, u32)) -> (u32, u32) {
+	let (x0, y0, dx, dy) = pass;
+	((width + dx - 1 - x0) / dx, (height + dy - 1 - y0) / dy)
 }
 
-// ****************************************************
-// 	Recon(x) = Filt(x)
-// ****************************************************
-fn none_defilter(chunk: &[u8], decode: &mut Vec<u8>) {
-	for i in 1..chunk.len() {
-		decode.push(chunk[i])
-	}
-}
+fn get_deinterlaced(inflated: &[u8], ihdr: &PNG_IHDR, channels: usize, idat_offset: u64) -> Result<Vec<u16>> {
+	let mut raw = vec![0; ihdr.width as usize * ihdr.height as usize * channels];
+	let mut offset = 0;
+	for &pass in ADAM7.iter() {
+		let (pass_width, pass_height) = get_pass_size(ihdr.width, ihdr.height, pass);
+		// Passes Without Pixels Are Skipped Entirely
+		if pass_width == 0 || pass_height == 0 {
+			continue;
+		}
 
-// ***************************************************************
-// 	Recon(x) = Filt(x) + Recon(a)
-// ***************************************************************
-fn sub_defilter(chunk: &[u8], depth: u8, decode: &mut Vec<u8>) {
-	let mut de_filterd = vec![];
-	for i in 1..chunk.len() {
-		if i <= (depth / 2) as usize {
-			de_filterd.push(chunk[i]);
-		} else {
-			let recon_x = Wrapping(chunk[i]) + Wrapping(de_filterd[i - 9]);
-			de_filterd.push(recon_x.0);
+		let pass_bytes = (get_row_bytes(pass_width, channels, ihdr.depth) + 1) * pass_height as usize;
+		if offset + pass_bytes > inflated.len() {
+			return Err(Error::Truncated { chunk: ffi::IDAT, offset: idat_offset });
+		}
+		let decode = match get_unfilterd_idat(&inflated[offset..offset + pass_bytes], pass_width, ihdr.depth, channels, idat_offset) {
+			Ok(decode) => decode,
+			Err(e) => return Err(e),
+		};
+		offset += pass_bytes;
+
+		// Scatter Pass Pixels Into The Full Image
+		let pass_raw = get_raw_samples(&decode, pass_width, ihdr.depth, channels);
+		let (x0, y0, dx, dy) = pass;
+		for j in 0..pass_height {
+			for i in 0..pass_width {
+				let src = (i as usize + j as usize * pass_width as usize) * channels;
+				let dst = ((x0 + i * dx) as usize + (y0 + j * dy) as usize * ihdr.width as usize) * channels;
+				raw[dst..dst + channels].copy_from_slice(&pass_raw[src..src + channels]);
+			}
 		}
-		decode.push(de_filterd[i - 1]);
 	}
+	Ok(raw)
 }
 
-// ***************************************************************
-// 	Recon(x) = Filt(x) + Recon(b)
-// ***************************************************************
-fn up_defilter(chunk: &[u8], width: u32, depth: u8, decode: &mut Vec<u8>) {
-	let mut offset: usize = 0x00;
-	let first_line: bool = if decode.len() == 0 { true } else { false };
-	if decode.len() != 0 {
-		offset = decode.len() - ((width * depth as u32) / 2) as usize;
-	}
-
-	for i in 1..chunk.len() {
-		if first_line {
-			decode.push(chunk[i]);
-		} else {
-			let recon_x = Wrapping(chunk[i]) + Wrapping(decode[offset + (i - 1)]);
-			decode.push(recon_x.0);
-		}
+// ************************************************************************************
+// x=the byte being filtered;
+// a=the byte corresponding to x in the pixel immediately before the pixel containing x
+// b=the byte corresponding to x in the previous scanline;
+// c=the byte corresponding to b in the pixel immediately before the pixel containing b
+// ************************************************************************************
+fn get_unfilterd_idat(inflated: &[u8], width: u32, depth: u8, channels: usize, idat_offset: u64) -> Result<Vec<u8>> {
+	let bpp = get_bytes_per_pixel(channels, depth);
+	let row_bytes = get_row_bytes(width, channels, depth);
+
+	let mut decode = Vec::<u8>::with_capacity(inflated.len());
+	for c in inflated.chunks(row_bytes + 1) {
+		let filter_type = match FilterType::from_u8(c[0]) {
+			Some(filter_type) => filter_type,
+			None => return Err(Error::InvalidChunk { chunk: ffi::IDAT, offset: idat_offset, reason: "Invalid Filter Type" }),
+		};
 
+		// The First Row Has No Previous Row, unfilter_row Treats It As Zeros
+		let start = decode.len();
+		decode.extend_from_slice(&c[1..]);
+		let (prev, row) = decode.split_at_mut(start);
+		filter::unfilter_row(filter_type, bpp, &prev[start.saturating_sub(row_bytes)..], row);
 	}
 
+	Ok(decode)
 }
 
-// ***************************************************************
-// Recon(x) = Filt(x) + floor((Recon(a) + Recon(b)) / 2)
-// ***************************************************************
-fn avg_defilter(chunk: &[u8], width: u32, depth: u8, decode: &mut Vec<u8>) {
-	let mut de_filterd = vec![];
-	let mut offset: usize = 0x00;
-	let first_line: bool = if decode.len() == 0 { true } else { false };
-	if decode.len() != 0 {
-		offset = decode.len() - ((width * depth as u32) / 2) as usize;
-	}
-
-	let pix_size: usize = (depth / 2) as usize;
-	for i in 1..chunk.len() {
-		if first_line {
-			if i <= pix_size {
-				de_filterd.push(chunk[i]);
-			} else {
-				let recon_x = Wrapping(chunk[i]) + Wrapping(de_filterd[i - 9] >> 1);
-				de_filterd.push(recon_x.0);
+// ************************************************************************************
+// Conver to u16 Without Scaling, Palette Indices Stay Indices
+// Sub Byte Samples Are Packed From The Most Significant Bit
+// ************************************************************************************
+fn get_raw_samples(decode: &[u8], width: u32, depth: u8, channels: usize) -> Vec<u16> {
+	let row_samples = width as usize * channels;
+	let mut samples = Vec::<u16>::new();
+	for row in decode.chunks(get_row_bytes(width, channels, depth)) {
+		if depth == DEPTH_16 {
+			let mut count = 0;
+			while count < row.len() {
+				samples.push(((row[count] as u16) << 8) + row[count + 1] as u16);
+				count += 2;
 			}
-
 		} else {
-			if i <= pix_size {
-				let recon_x = Wrapping(chunk[i]) + Wrapping(decode[offset + (i - 1)] >> 1);
-				de_filterd.push(recon_x.0);
-			} else {
-				let floor_ab = decode[offset + (i - 1)] as i16 + de_filterd[i - 9] as i16;
-				let recon_x = chunk[i] as i16 + (floor_ab >> 1);
-				de_filterd.push(recon_x as u8);
+			for i in 0..row_samples {
+				samples.push(get_packed_sample(row, i, depth as usize) as u16);
 			}
-
 		}
-		decode.push(de_filterd[i - 1] as u8);
-
 	}
+	samples
 }
 
-// ******************************************************************
-// Recon(x) = Filt(x) + PaethPredictor(Recon(a), Recon(b), Recon(c))
-// ******************************************************************
-fn paeth_defilter(chunk: &[u8], width: u32, depth: u8, decode: &mut Vec<u8>) {
-	let mut de_filterd = vec![];
-	let (mut a, mut b, mut c, mut p, mut pa, mut pb, mut pc, mut pr): (i64, i64, i64, i64, i64, i64, i64, i64);
-
-	let offset = decode.len() - ((width * depth as u32) / 2) as usize;
-	for i in 1..chunk.len() {
-		if i <= (depth / 2) as usize {
-			a = 0x00;
-			b = decode[offset + (i - 1)] as i64;
-			c = 0x00;
-
-			// PaethPredictor ()
-			p = a + b - c;
-			pa = (p - a).abs();
-			pb = (p - b).abs();
-			pc = (p - c).abs();
+// ************************************************************************************
+// Samples Are Widened To Cover The Full 16bit Range
+// 8bit: v * 257, 4bit: v * 4369, 2bit: v * 21845, 1bit: v * 65535
+// ************************************************************************************
+fn get_samples(raw: Vec<u16>, depth: u8) -> Vec<u16> {
+	let scale = (0xFFFF / ((1u32 << depth) - 1)) as u16;
+	// Widened In Place, No Second Buffer
+	raw.into_iter().map(|v| v * scale).collect()
+}
 
-			if pa <= pb && pa <= pc {
-				pr = (chunk[i] as i64 + a as i64) as i64;
-			} else if pb <= pc {
-				pr = (chunk[i] as i64 + b as i64) as i64;
-			} else {
-				pr = (chunk[i] as i64 + c as i64) as i64;
-			}
-			de_filterd.push(pr as u8);
+// Sample i Of A Row With Depth <= 8
+fn get_packed_sample(row: &[u8], i: usize, depth: usize) -> usize {
+	let bit = i * depth;
+	(row[bit / 8] as usize >> (8 - depth - bit % 8)) & ((1 << depth) - 1)
+}
 
-		} else {
-			a = de_filterd[i - 9] as i64;
-			b = decode[offset + (i - 1)] as i64;
-			c = decode[offset + (i - 9)] as i64;
-			p = a + b - c;
-			pa = (p - a).abs();
-			pb = (p - b).abs();
-			pc = (p - c).abs();
-
-			if pa <= pb && pa <= pc {
-				pr = (chunk[i] as i64 + a as i64) as i64;
-			} else if pb <= pc {
-				pr = (chunk[i] as i64 + b as i64) as i64;
-			} else {
-				pr = (chunk[i] as i64 + c as i64) as i64;
-			}
+fn filter_rgba(png: &PNG, depth: u8, color_type: u8, strategy: FilterStrategy) -> Result<Vec<u8>> {
+	let channels = get_channels(color_type);
+	let bpp = get_bytes_per_pixel(channels, depth);
 
-			de_filterd.push(pr as u8);
+	if png.ihdr.interlace == 0 {
+		let mut rgba = Vec::<u8>::new();
+		for pixel in png.pixels() {
+			push_pixel(&mut rgba, pixel, depth, color_type);
 		}
-
-		decode.push(de_filterd[i - 1]);
+		return filter_scanlines(&rgba, get_row_bytes(png.ihdr.width, channels, depth), bpp, strategy);
 	}
 
-}
-
-fn filter_rgba(png: &mut PNG, depth: u8) -> Result<Vec<u8>> {
-	let mut b_chunk = vec![];
+	// Adam7: Every Pass Is Filtered On Its Own, Empty Passes Are Skipped
 	let mut main = Vec::<u8>::new();
-
-	let mut rgba = Vec::<u8>::new();
-	if depth == DEPTH_16 {
-		for j in 0..(png.ihdr.width * png.ihdr.height) as usize {
-			for i in 0..3 {
-				rgba.push((png.rgb[i + (3 * j)] >> 8) as u8);
-				rgba.push(png.rgb[i + (3 * j)] as u8);
-			}
-
-			rgba.push((png.alpha[j] >> 8) as u8);
-			rgba.push(png.alpha[j] as u8);
+	for &pass in ADAM7.iter() {
+		let (pass_width, pass_height) = get_pass_size(png.ihdr.width, png.ihdr.height, pass);
+		if pass_width == 0 || pass_height == 0 {
+			continue;
 		}
-	} else {
-		for j in 0..(png.ihdr.width * png.ihdr.height) as usize {
-			for i in 0..3 {
-				rgba.push(((png.rgb[i + (3 * j)] >> 8) as f32).round() as u8);
 
+		let (x0, y0, dx, dy) = pass;
+		let mut rgba = Vec::<u8>::new();
+		for j in 0..pass_height {
+			for i in 0..pass_width {
+				push_pixel(&mut rgba, png.get_pixel(x0 + i * dx, y0 + j * dy), depth, color_type);
 			}
-			rgba.push(((png.alpha[j] >> 8) as f32).round() as u8);
-
-		}
-	}
-	// Scanline Total Bytes
-	for c in rgba.chunks(((png.ihdr.width * depth as u32) / 2) as usize) {
-		// Test Every Filter + Compression For Smallest Size
-		let none = match apply_none_filter(&c) {
-			Ok(none) => none,
-			Err(e) => return Err(e),
-		};
-		let sub = match apply_sub_filter(&c, depth) {
-			Ok(sub) => sub,
-			Err(e) => return Err(e),
-		};
-		let up = match apply_up_filter(&c, &b_chunk) {
-			Ok(up) => up,
-			Err(e) => return Err(e),
-		};
-		let avg = match apply_avg_filter(&c, &b_chunk, depth) {
-			Ok(avg) => avg,
-			Err(e) => return Err(e),
-		};
-		let paeth = match apply_paeth_filter(&c, &b_chunk, depth) {
-			Ok(paeth) => paeth,
-			Err(e) => return Err(e),
-		};
-
-		let (mut none_deflated, mut sub_deflated, mut up_deflated, mut avg_deflated, mut paeth_deflated) = (vec![], vec![], vec![], vec![], vec![]);
-
-		match none.zlib_encode(Compression::Best).read_to_end(&mut none_deflated) {
-			Ok(_) => (),
-			Err(e) => return Err(e),
-		};
-		match sub.zlib_encode(Compression::Best).read_to_end(&mut sub_deflated) {
-			Ok(_) => (),
-			Err(e) => return Err(e),
-		};
-		match up.zlib_encode(Compression::Best).read_to_end(&mut up_deflated) {
-			Ok(_) => (),
-			Err(e) => return Err(e),
-		};
-		match avg.zlib_encode(Compression::Best).read_to_end(&mut avg_deflated) {
-			Ok(_) => (),
-			Err(e) => return Err(e),
-		};
-		match paeth.zlib_encode(Compression::Best).read_to_end(&mut paeth_deflated) {
-			Ok(_) => (),
-			Err(e) => return Err(e),
-		};
-
-		if paeth_deflated.len() <= avg_deflated.len() && paeth_deflated.len() <= up_deflated.len() && paeth_deflated.len() <= sub_deflated.len() && paeth_deflated.len() <= none_deflated.len() {
-			match main.write(&paeth) {
-				Ok(_) => (),
-				Err(e) => return Err(e),
-			};
-		} else if avg_deflated.len() <= up_deflated.len() && avg_deflated.len() <= sub_deflated.len() && avg_deflated.len() <= none_deflated.len() {
-			match main.write(&avg) {
-				Ok(_) => (),
-				Err(e) => return Err(e),
-			};
-		} else if up_deflated.len() <= sub_deflated.len() && up_deflated.len() <= none_deflated.len() {
-			match main.write(&up) {
-				Ok(_) => (),
-				Err(e) => return Err(e),
-			};
-		} else if sub_deflated.len() <= none_deflated.len() {
-			match main.write(&sub) {
-				Ok(_) => (),
-				Err(e) => return Err(e),
-			};
-		} else {
-			match main.write(&none) {
-				Ok(_) => (),
-				Err(e) => return Err(e),
-			};
 		}
 
-		b_chunk.clear();
-		match b_chunk.write(&c) {
-			Ok(_) => (),
+		match filter_scanlines(&rgba, get_row_bytes(pass_width, channels, depth), bpp, strategy) {
+			Ok(filterd) => main.extend_from_slice(&filterd),
 			Err(e) => return Err(e),
 		};
-
 	}
 	Ok(main)
 }
 
-// ***************************************************************
-// Filt(x) = Orig(x)
-// ***************************************************************
-fn apply_none_filter(chunk: &[u8]) -> Result<Vec<u8>> {
-	let mut filterd = vec![];
-	filterd.push(0x00);
-	for &b in chunk {
-		filterd.push(b);
+// Gray Output Takes R, Only Written When R, G And B Agree
+fn push_pixel(rgba: &mut Vec<u8>, pixel: Rgba16, depth: u8, color_type: u8) {
+	if color_type & 0x02 == 0 {
+		push_sample(rgba, pixel.r, depth);
+	} else {
+		push_sample(rgba, pixel.r, depth);
+		push_sample(rgba, pixel.g, depth);
+		push_sample(rgba, pixel.b, depth);
 	}
-	Ok(filterd)
-}
-
-// *********************************************************************
-// Filt(x) = Orig(x) - Orig(a)
-// *********************************************************************
-fn apply_sub_filter(chunk: &[u8], depth: u8) -> Result<Vec<u8>> {
-	let mut filterd = vec![];
-	filterd.push(0x01);
-	for i in 0..chunk.len() {
-		if i < (depth >> 1) as usize {
-			filterd.push(chunk[i] as u8);
-		} else {
-			let filt_x = Wrapping(chunk[i]) - Wrapping(chunk[i - (depth >> 1) as usize]);
-			filterd.push(filt_x.0);
-		}
+	if color_type & 0x04 != 0 {
+		push_sample(rgba, pixel.a, depth);
 	}
-	Ok(filterd)
 }
 
-// *********************************************************************
-// Filt(x) = Orig(x) - Orig(b)
-// *********************************************************************
-fn apply_up_filter(a_chunk: &[u8], b_chunk: &[u8]) -> Result<Vec<u8>> {
-	let mut filterd = vec![];
-	filterd.push(0x02);
-	for i in 0..a_chunk.len() as usize {
-		if a_chunk.len() == b_chunk.len() {
-			filterd.push((a_chunk[i] as i16 - b_chunk[i] as i16) as u8);
-		} else {
-			filterd.push(a_chunk[i]);
-		}
-	}
-	Ok(filterd)
-}
-
-// *********************************************************************************
-// Filt(x) = Orig(x) - floor((Orig(a) + Orig(b)) / 2)
-// *********************************************************************************
-fn apply_avg_filter(a_chunk: &[u8], b_chunk: &[u8], depth: u8) -> Result<Vec<u8>> {
-	let mut filterd = vec![];
-	filterd.push(0x03);
-	for i in 0..a_chunk.len() as usize {
-		if a_chunk.len() == b_chunk.len() {
-			if i < (depth >> 1) as usize {
-				filterd.push((a_chunk[i] as f32 - (b_chunk[i] as f32 / 2.0_f32).floor()) as u8);
-			} else {
-				filterd.push((a_chunk[i] as f32 - ((a_chunk[i - (depth >> 1) as usize] as f32 + b_chunk[i] as f32 / 2.0_f32).floor())) as u8);
-			}
-		} else {
-			if i < (depth >> 1) as usize {
-				filterd.push(a_chunk[i]);
-			} else {
-				let result = {
-					Wrapping(a_chunk[i]) - (Wrapping(a_chunk[i - (depth >> 1) as usize] >> 1))
-				};
-				filterd.push(result.0);
-			}
-		}
+fn filter_scanlines(rgba: &[u8], row_bytes: usize, bpp: usize, strategy: FilterStrategy) -> Result<Vec<u8>> {
+	let mut prev: &[u8] = &[];
+	let mut main = Vec::<u8>::with_capacity(rgba.len() + rgba.len() / row_bytes);
+
+	// Scanline Total Bytes
+	for c in rgba.chunks(row_bytes) {
+		match filter::filter_row_with_strategy(strategy, bpp, prev, c, &mut main) {
+			Ok(_) => (),
+			Err(e) => return Err(e),
+		};
+		prev = c;
 	}
-	Ok(filterd)
+	Ok(main)
 }
 
-// ***********************************************************************************
-// Filt(x) = Orig(x) - PaethPredictor(Orig(a), Orig(b), Orig(c))
-// ***********************************************************************************
-fn apply_paeth_filter(a_chunk: &[u8], b_chunk: &[u8], depth: u8) -> Result<Vec<u8>> {
-	let mut filterd = vec![];
-	filterd.push(0x04);
-	let (mut a, mut b, mut c, mut p, mut pa, mut pb, mut pc, mut pr): (i64, i64, i64, i64, i64, i64, i64, i64);
-
-
-	for i in 0..a_chunk.len() as usize {
-		if a_chunk.len() != b_chunk.len() {
-			if i < (depth >> 1) as usize {
-				filterd.push(a_chunk[i]);
-			} else {
-				let val = Wrapping(a_chunk[i]) - Wrapping(a_chunk[i - (depth >> 1) as usize]);
-				filterd.push(val.0);
-			}
-
-		} else {
-			if i < (depth >> 1) as usize {
-				a = 0x00;
-				b = b_chunk[i] as i64;
-				c = 0x00;
-				p = a + b - c;
-				pa = (p - a).abs();
-				pb = (p - b).abs();
-				pc = (p - c).abs();
-
-				if pa <= pb && pa <= pc {
-					pr = (a_chunk[i] as i64 - a as i64) as i64;
-				} else if pb <= pc {
-					pr = (a_chunk[i] as i64 - b as i64) as i64;
-				} else {
-					pr = (a_chunk[i] as i64 - c as i64) as i64;
-				}
-				filterd.push(pr as u8);
-			} else {
-				a = a_chunk[i - (depth >> 1) as usize] as i64;
-				b = b_chunk[i] as i64;
-				c = b_chunk[i - (depth >> 1) as usize] as i64;
-				p = a + b - c;
-				pa = (p - a).abs();
-				pb = (p - b).abs();
-				pc = (p - c).abs();
-
-				if pa <= pb && pa <= pc {
-					pr = (a_chunk[i] as i64 - a as i64) as i64;
-				} else if pb <= pc {
-					pr = (a_chunk[i] as i64 - b as i64) as i64;
-				} else {
-					pr = (a_chunk[i] as i64 - c as i64) as i64;
-				}
-				filterd.push(pr as u8);
-			}
-		}
+fn push_sample(rgba: &mut Vec<u8>, sample: u16, depth: u8) {
+	if depth == DEPTH_16 {
+		rgba.push((sample >> 8) as u8);
+		rgba.push(sample as u8);
+	} else {
+		rgba.push((sample >> 8) as u8);
 	}
-	Ok(filterd)
 }
 
 pub trait SeekableReader: Seek + Read {}
@@ -0,0 +1,478 @@
+use byteorder::{ByteOrder, BigEndian};
+use flate2::{Compression, FlateReadExt};
+use std::io::prelude::*;
+
+use error::{Error, Result};
+use ffi;
+use super::{get_chunk, get_chunk_data, get_header_chunks, PNG_IHDR};
+use super::{COLOR_TYPE_GRAY, COLOR_TYPE_GRAY_ALPHA, COLOR_TYPE_PALETTE, COLOR_TYPE_RGB, COLOR_TYPE_RGBA, DEPTH_8};
+use text::{self, TextChunk};
+
+// ********************************************************
+// Typed Ancillary Chunks, None When The Chunk Is Absent
+// Parsed Chunks Are Removed From PNG.header And Regenerated On Encode
+// ********************************************************
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+	// gAMA: Image Gamma (Stored As Gamma * 100000)
+	pub gamma: Option<f64>,
+	// cHRM: CIE x,y Of The White Point And Primaries
+	pub chromaticities: Option<Chromaticities>,
+	// sRGB: Rendering Intent
+	pub srgb: Option<RenderingIntent>,
+	// iCCP: Embedded ICC Profile, Stored Uncompressed
+	pub icc_profile: Option<IccProfile>,
+	// pHYs: Pixels Per Unit
+	pub physical: Option<PhysicalDimensions>,
+	// tIME: Last Modification (UTC)
+	pub time: Option<Timestamp>,
+	// tIME Was Found After IDAT And Is Written Back There
+	pub time_after_idat: bool,
+	// bKGD: Background Color Widened To 16bit RGB, Palette Entries Are Resolved
+	pub background: Option<[u16; 3]>,
+	// sBIT: Significant Bits As [R, G, B, A], Grayscale Is Copied Into R, G And B
+	pub significant_bits: Option<[u8; 4]>,
+	// tEXt, zTXt And iTXt In File Order, Keywords May Repeat
+	pub text: Vec<TextChunk>,
+	// Types Of The Chunks After IDAT In File Order, Typed And Opaque Alike,
+	// Encode Interleaves tIME, Text And PNG.trailer By It
+	pub after_idat_order: Vec<u32>,
+}
+
+impl Metadata {
+	// First Value Stored Under keyword
+	pub fn get_text(&self, keyword: &str) -> Option<&str> {
+		self.text.iter().find(|chunk| chunk.keyword == keyword).map(|chunk| chunk.text.as_str())
+	}
+
+	// Text Outside Latin-1 Moves A tEXt/zTXt Chunk To iTXt
+	pub fn add_text(&mut self, mut chunk: TextChunk) -> Result<bool> {
+		if !text::is_valid_keyword(&chunk.keyword) {
+			return Err(Error::InvalidChunk { chunk: ffi::tEXt, offset: 0, reason: "Invalid Keyword" });
+		}
+		chunk.kind = text::get_text_kind(chunk.kind, &chunk.text);
+		self.text.push(chunk);
+		Ok(true)
+	}
+
+	// ********************************************************
+	// Replaces The First Value Under keyword And Drops Any Others,
+	// Adds A New Chunk When keyword Is Missing
+	// Text Outside Latin-1 Moves A tEXt/zTXt Chunk To iTXt
+	// ********************************************************
+	pub fn set_text(&mut self, keyword: &str, value: &str) -> Result<bool> {
+		let index = match self.text.iter().position(|chunk| chunk.keyword == keyword) {
+			Some(index) => index,
+			None => return self.add_text(TextChunk::new(keyword, value)),
+		};
+
+		let mut chunk = self.text[index].clone();
+		chunk.kind = text::get_text_kind(chunk.kind, value);
+		chunk.text = value.to_string();
+		self.remove_text(keyword);
+		self.text.insert(index, chunk);
+		Ok(true)
+	}
+
+	// Removes Every Value Under keyword, False When There Was None
+	pub fn remove_text(&mut self, keyword: &str) -> bool {
+		let count = self.text.len();
+		self.text.retain(|chunk| chunk.keyword != keyword);
+		self.text.len() != count
+	}
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Chromaticities {
+	pub white: (f64, f64),
+	pub red: (f64, f64),
+	pub green: (f64, f64),
+	pub blue: (f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderingIntent {
+	Perceptual,
+	RelativeColorimetric,
+	Saturation,
+	AbsoluteColorimetric,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IccProfile {
+	// Latin-1, 1 To 79 Characters
+	pub name: String,
+	pub profile: Vec<u8>,
+}
+
+// unit 0: Aspect Ratio Only, unit 1: Pixels Per Meter
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PhysicalDimensions {
+	pub x: u32,
+	pub y: u32,
+	pub unit: u8,
+}
+
+impl PhysicalDimensions {
+	pub fn from_dpi(x: f64, y: f64) -> PhysicalDimensions {
+		PhysicalDimensions { x: (x / 0.0254).round() as u32, y: (y / 0.0254).round() as u32, unit: 1 }
+	}
+
+	// None When The Unit Is Unknown
+	pub fn dpi(&self) -> Option<(f64, f64)> {
+		if self.unit != 1 {
+			return None;
+		}
+		Some((self.x as f64 * 0.0254, self.y as f64 * 0.0254))
+	}
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Timestamp {
+	pub year: u16,
+	pub month: u8,
+	pub day: u8,
+	pub hour: u8,
+	pub minute: u8,
+	pub second: u8,
+}
+
+// ********************************************************
+// Moves Typed Chunks From A Chunk Blob Into metadata, Returns The Chunks Left Opaque
+// After IDAT Only tIME And Text Are Typed, Their Position Is Remembered
+// Malformed Ancillary Chunks Are Dropped With An InvalidChunk Warning
+// inflate_budget: Bytes Of max_metadata_bytes Not Yet Inflated, Shared By The Whole File
+// ********************************************************
+pub(crate) fn get_metadata(header: &[u8], base_offset: u64, ihdr: &PNG_IHDR, inflate_budget: &mut u64, metadata: &mut Metadata, after_idat: bool, warnings: &mut Vec<Error>) -> Result<Vec<u8>> {
+	let mut rest = Vec::<u8>::new();
+
+	let chunks = get_header_chunks(header);
+	let plte = match chunks.iter().find(|&&(chunk_type, _)| chunk_type == ffi::PLTE) {
+		Some(&(_, plte)) => get_chunk_data(plte),
+		None => &[],
+	};
+
+	for &(chunk_type, chunk) in chunks.iter() {
+		let offset = base_offset + (chunk.as_ptr() as usize - header.as_ptr() as usize) as u64;
+		let data = get_chunk_data(chunk);
+		if after_idat && ![ffi::tIME, ffi::tEXt, ffi::zTXt, ffi::iTXt].contains(&chunk_type) {
+			rest.extend_from_slice(chunk);
+			metadata.after_idat_order.push(chunk_type);
+			continue;
+		}
+		let valid = match chunk_type {
+			ffi::gAMA => get_gamma(data).map(|gamma| metadata.gamma = Some(gamma)),
+			ffi::cHRM => get_chromaticities(data).map(|chrm| metadata.chromaticities = Some(chrm)),
+			ffi::sRGB => get_rendering_intent(data).map(|intent| metadata.srgb = Some(intent)),
+			ffi::iCCP => match get_icc_profile(data, chunk_type, offset, inflate_budget) {
+				Ok(icc) => icc.map(|icc| metadata.icc_profile = Some(icc)),
+				Err(e) => return Err(e),
+			},
+			ffi::pHYs => get_physical(data).map(|phys| metadata.physical = Some(phys)),
+			ffi::tIME => get_timestamp(data).map(|time| {
+				metadata.time = Some(time);
+				metadata.time_after_idat = after_idat;
+			}),
+			ffi::bKGD => get_background(data, ihdr, plte).map(|bkgd| metadata.background = Some(bkgd)),
+			ffi::sBIT => get_significant_bits(data, ihdr).map(|sbit| metadata.significant_bits = Some(sbit)),
+			ffi::tEXt | ffi::zTXt | ffi::iTXt => match text::get_text_chunk(chunk_type, data, offset, inflate_budget) {
+				Ok(chunk) => chunk.map(|mut chunk| {
+					chunk.after_idat = after_idat;
+					metadata.text.push(chunk);
+				}),
+				Err(e) => return Err(e),
+			},
+			_ => {
+				rest.extend_from_slice(chunk);
+				Some(())
+			},
+		};
+		if valid.is_none() {
+			warnings.push(Error::InvalidChunk { chunk: chunk_type, offset, reason: "Malformed Ancillary Chunk" });
+		} else if after_idat {
+			metadata.after_idat_order.push(chunk_type);
+		}
+	}
+
+	Ok(rest)
+}
+
+// Clears Every Typed Field Whose Chunk Type Is Not In keep
+pub(crate) fn get_stripped_metadata(metadata: &Metadata, keep: &[u32]) -> Metadata {
+	let kept = |chunk_type: u32| keep.contains(&chunk_type);
+	Metadata {
+		gamma: metadata.gamma.filter(|_| kept(ffi::gAMA)),
+		chromaticities: metadata.chromaticities.filter(|_| kept(ffi::cHRM)),
+		srgb: metadata.srgb.filter(|_| kept(ffi::sRGB)),
+		icc_profile: metadata.icc_profile.clone().filter(|_| kept(ffi::iCCP)),
+		physical: metadata.physical.filter(|_| kept(ffi::pHYs)),
+		time: metadata.time.filter(|_| kept(ffi::tIME)),
+		time_after_idat: metadata.time_after_idat,
+		background: metadata.background.filter(|_| kept(ffi::bKGD)),
+		significant_bits: metadata.significant_bits.filter(|_| kept(ffi::sBIT)),
+		text: metadata.text.iter().filter(|chunk| kept(text::get_chunk_type(chunk))).cloned().collect(),
+		after_idat_order: metadata.after_idat_order.clone(),
+	}
+}
+
+fn get_gamma(data: &[u8]) -> Option<f64> {
+	if data.len() != 4 {
+		return None;
+	}
+	Some(BigEndian::read_u32(data) as f64 / 100000.0)
+}
+
+fn get_chromaticities(data: &[u8]) -> Option<Chromaticities> {
+	if data.len() != 32 {
+		return None;
+	}
+	let point = |i: usize| (BigEndian::read_u32(&data[i * 8..]) as f64 / 100000.0, BigEndian::read_u32(&data[i * 8 + 4..]) as f64 / 100000.0);
+	Some(Chromaticities { white: point(0), red: point(1), green: point(2), blue: point(3) })
+}
+
+fn get_rendering_intent(data: &[u8]) -> Option<RenderingIntent> {
+	if data.len() != 1 {
+		return None;
+	}
+	match data[0] {
+		0 => Some(RenderingIntent::Perceptual),
+		1 => Some(RenderingIntent::RelativeColorimetric),
+		2 => Some(RenderingIntent::Saturation),
+		3 => Some(RenderingIntent::AbsoluteColorimetric),
+		_ => None,
+	}
+}
+
+// Profile Name, Null Separator, Compression Method 0, zlib Stream
+fn get_icc_profile(data: &[u8], chunk_type: u32, offset: u64, inflate_budget: &mut u64) -> Result<Option<IccProfile>> {
+	let name_len = match data.iter().position(|&b| b == 0) {
+		Some(name_len) => name_len,
+		None => return Ok(None),
+	};
+	if name_len == 0 || name_len > 79 || data.len() < name_len + 2 || data[name_len + 1] != 0 {
+		return Ok(None);
+	}
+
+	match get_inflated(&data[name_len + 2..], chunk_type, offset, inflate_budget) {
+		Ok(Some(profile)) => Ok(Some(IccProfile { name: get_latin1(&data[..name_len]), profile })),
+		Ok(None) => Ok(None),
+		Err(e) => Err(e),
+	}
+}
+
+// ********************************************************
+// None When The zlib Stream Is Corrupt
+// Inflating Past What Is Left Of max_metadata_bytes Means A Decompression Bomb,
+// Many Small Chunks Add Up The Same As One Large Chunk
+// ********************************************************
+pub(crate) fn get_inflated(data: &[u8], chunk_type: u32, offset: u64, inflate_budget: &mut u64) -> Result<Option<Vec<u8>>> {
+	let mut inflated = vec![];
+	match data.zlib_decode().take(*inflate_budget + 1).read_to_end(&mut inflated) {
+		Ok(_) => (),
+		Err(_) => return Ok(None),
+	};
+	if inflated.len() as u64 > *inflate_budget {
+		return Err(Error::LimitExceeded { chunk: chunk_type, offset, limit: "Metadata Bytes" });
+	}
+	*inflate_budget -= inflated.len() as u64;
+	Ok(Some(inflated))
+}
+
+// Errors Name The Chunk Being Built
+pub(crate) fn get_compressed(data: &[u8], chunk_type: u32) -> Result<Vec<u8>> {
+	let mut deflated = vec![];
+	match data.zlib_encode(Compression::Default).read_to_end(&mut deflated) {
+		Ok(_) => Ok(deflated),
+		Err(e) => Err(Error::Zlib { chunk: chunk_type, offset: 0, message: e.to_string() }),
+	}
+}
+
+fn get_physical(data: &[u8]) -> Option<PhysicalDimensions> {
+	if data.len() != 9 || data[8] > 1 {
+		return None;
+	}
+	Some(PhysicalDimensions { x: BigEndian::read_u32(data), y: BigEndian::read_u32(&data[4..]), unit: data[8] })
+}
+
+fn get_timestamp(data: &[u8]) -> Option<Timestamp> {
+	if data.len() != 7 {
+		return None;
+	}
+	Some(Timestamp { year: BigEndian::read_u16(data), month: data[2], day: data[3], hour: data[4], minute: data[5], second: data[6] })
+}
+
+// Palette Images Store An Index, Everything Else Stores Samples At The Image Depth
+fn get_background(data: &[u8], ihdr: &PNG_IHDR, plte: &[u8]) -> Option<[u16; 3]> {
+	if ihdr.color_type == COLOR_TYPE_PALETTE {
+		if data.len() != 1 || data[0] as usize * 3 + 3 > plte.len() {
+			return None;
+		}
+		let entry = &plte[data[0] as usize * 3..];
+		return Some([entry[0] as u16 * 257, entry[1] as u16 * 257, entry[2] as u16 * 257]);
+	}
+
+	let max = ((1u32 << ihdr.depth) - 1) as u16;
+	let scale = (0xFFFF / max as u32) as u16;
+	let sample = |i: usize| (BigEndian::read_u16(&data[i * 2..]) & max) * scale;
+	match ihdr.color_type {
+		COLOR_TYPE_GRAY | COLOR_TYPE_GRAY_ALPHA if data.len() == 2 => Some([sample(0), sample(0), sample(0)]),
+		COLOR_TYPE_RGB | COLOR_TYPE_RGBA if data.len() == 6 => Some([sample(0), sample(1), sample(2)]),
+		_ => None,
+	}
+}
+
+// Channels Without sBIT Entries Are Significant At The Sample Depth
+fn get_significant_bits(data: &[u8], ihdr: &PNG_IHDR) -> Option<[u8; 4]> {
+	let depth = if ihdr.color_type == COLOR_TYPE_PALETTE { DEPTH_8 } else { ihdr.depth };
+	if data.iter().any(|&bits| bits == 0 || bits > depth) {
+		return None;
+	}
+	match (ihdr.color_type, data.len()) {
+		(COLOR_TYPE_GRAY, 1) => Some([data[0], data[0], data[0], depth]),
+		(COLOR_TYPE_GRAY_ALPHA, 2) => Some([data[0], data[0], data[0], data[1]]),
+		(COLOR_TYPE_RGB, 3) | (COLOR_TYPE_PALETTE, 3) => Some([data[0], data[1], data[2], depth]),
+		(COLOR_TYPE_RGBA, 4) => Some([data[0], data[1], data[2], data[3]]),
+		_ => None,
+	}
+}
+
+pub(crate) fn get_latin1(bytes: &[u8]) -> String {
+	bytes.iter().map(|&b| b as char).collect()
+}
+
+// Characters Outside Latin-1 Are Written As '?'
+pub(crate) fn get_latin1_bytes(text: &str) -> Vec<u8> {
+	text.chars().map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' }).collect()
+}
+
+// ********************************************************
+// cHRM, gAMA, iCCP, sBIT, sRGB: The Spec Requires These Before PLTE
+// ********************************************************
+pub(crate) fn get_pre_plte_chunks(metadata: &Metadata, depth: u8, color_type: u8) -> Result<Vec<u8>> {
+	let mut chunks = Vec::<u8>::new();
+
+	if let Some(chrm) = metadata.chromaticities {
+		let mut data = vec![0; 32];
+		for (i, &(x, y)) in [chrm.white, chrm.red, chrm.green, chrm.blue].iter().enumerate() {
+			BigEndian::write_u32(&mut data[i * 8..], (x * 100000.0).round() as u32);
+			BigEndian::write_u32(&mut data[i * 8 + 4..], (y * 100000.0).round() as u32);
+		}
+		chunks.extend_from_slice(&get_chunk(ffi::cHRM, &data));
+	}
+	if let Some(gamma) = metadata.gamma {
+		let mut data = vec![0; 4];
+		BigEndian::write_u32(&mut data, (gamma * 100000.0).round() as u32);
+		chunks.extend_from_slice(&get_chunk(ffi::gAMA, &data));
+	}
+	if let Some(ref icc) = metadata.icc_profile {
+		let mut data = get_latin1_bytes(&icc.name);
+		data.truncate(79);
+		data.extend_from_slice(&[0, 0]);
+		match get_compressed(&icc.profile, ffi::iCCP) {
+			Ok(profile) => data.extend_from_slice(&profile),
+			Err(e) => return Err(e),
+		};
+		chunks.extend_from_slice(&get_chunk(ffi::iCCP, &data));
+	}
+	if let Some(sbit) = metadata.significant_bits {
+		let bits = |i: usize| std::cmp::min(sbit[i], depth);
+		let data = match color_type {
+			COLOR_TYPE_GRAY => vec![std::cmp::max(bits(0), std::cmp::max(bits(1), bits(2)))],
+			COLOR_TYPE_GRAY_ALPHA => vec![std::cmp::max(bits(0), std::cmp::max(bits(1), bits(2))), bits(3)],
+			COLOR_TYPE_RGB => vec![bits(0), bits(1), bits(2)],
+			_ => vec![bits(0), bits(1), bits(2), bits(3)],
+		};
+		chunks.extend_from_slice(&get_chunk(ffi::sBIT, &data));
+	}
+	if let Some(intent) = metadata.srgb {
+		chunks.extend_from_slice(&get_chunk(ffi::sRGB, &[intent as u8]));
+	}
+	Ok(chunks)
+}
+
+// ********************************************************
+// bKGD, pHYs, tIME And Text: Written After PLTE, Before IDAT
+// ********************************************************
+pub(crate) fn get_post_plte_chunks(metadata: &Metadata, depth: u8, color_type: u8) -> Result<Vec<u8>> {
+	let mut chunks = Vec::<u8>::new();
+
+	if let Some(bkgd) = metadata.background {
+		// Narrow Back To The Output Depth
+		let max = ((1u32 << depth) - 1) as u16;
+		let narrow = |v: u16| ((v as u32 * max as u32 + 0x7FFF) / 0xFFFF) as u16;
+		let samples = match color_type {
+			COLOR_TYPE_GRAY | COLOR_TYPE_GRAY_ALPHA => vec![narrow(bkgd[0])],
+			_ => vec![narrow(bkgd[0]), narrow(bkgd[1]), narrow(bkgd[2])],
+		};
+		let mut data = vec![0; samples.len() * 2];
+		for (i, &sample) in samples.iter().enumerate() {
+			BigEndian::write_u16(&mut data[i * 2..], sample);
+		}
+		chunks.extend_from_slice(&get_chunk(ffi::bKGD, &data));
+	}
+	if let Some(phys) = metadata.physical {
+		let mut data = vec![0; 9];
+		BigEndian::write_u32(&mut data, phys.x);
+		BigEndian::write_u32(&mut data[4..], phys.y);
+		data[8] = phys.unit;
+		chunks.extend_from_slice(&get_chunk(ffi::pHYs, &data));
+	}
+	if !metadata.time_after_idat {
+		chunks.extend_from_slice(&get_time_chunk(metadata));
+	}
+	match text::get_text_chunks(&metadata.text, false) {
+		Ok(text) => chunks.extend_from_slice(&text),
+		Err(e) => return Err(e),
+	};
+	Ok(chunks)
+}
+
+// ********************************************************
+// tIME And Text That Were Found After IDAT, Interleaved With The Opaque
+// Trailer Chunks In after_idat_order, Anything Not Listed Follows In That Order
+// An Opaque Entry Whose Chunk Was Stripped Is Skipped
+// ********************************************************
+pub(crate) fn get_after_idat_chunks(metadata: &Metadata, trailer: &[u8]) -> Result<Vec<u8>> {
+	let mut time = if metadata.time_after_idat { get_time_chunk(metadata) } else { vec![] };
+	let mut text = metadata.text.iter().filter(|chunk| chunk.after_idat);
+	let mut opaque = get_header_chunks(trailer).into_iter().peekable();
+
+	let mut chunks = Vec::<u8>::new();
+	for &chunk_type in metadata.after_idat_order.iter() {
+		match chunk_type {
+			ffi::tIME => chunks.append(&mut time),
+			ffi::tEXt | ffi::zTXt | ffi::iTXt => if let Some(chunk) = text.next() {
+				match text::get_text_chunks(std::slice::from_ref(chunk), true) {
+					Ok(text) => chunks.extend_from_slice(&text),
+					Err(e) => return Err(e),
+				};
+			},
+			_ => if let Some((_, chunk)) = opaque.next_if(|&(opaque_type, _)| opaque_type == chunk_type) {
+				chunks.extend_from_slice(chunk);
+			},
+		}
+	}
+
+	chunks.append(&mut time);
+	let rest: Vec<TextChunk> = text.cloned().collect();
+	match text::get_text_chunks(&rest, true) {
+		Ok(text) => chunks.extend_from_slice(&text),
+		Err(e) => return Err(e),
+	};
+	for (_, chunk) in opaque {
+		chunks.extend_from_slice(chunk);
+	}
+	Ok(chunks)
+}
+
+fn get_time_chunk(metadata: &Metadata) -> Vec<u8> {
+	match metadata.time {
+		Some(time) => {
+			let mut data = vec![0; 7];
+			BigEndian::write_u16(&mut data, time.year);
+			data[2..].copy_from_slice(&[time.month, time.day, time.hour, time.minute, time.second]);
+			get_chunk(ffi::tIME, &data)
+		},
+		None => vec![],
+	}
+}
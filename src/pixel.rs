@@ -0,0 +1,218 @@
+use std::marker::PhantomData;
+
+use image::ImageBuffer;
+
+// ********************************************************
+// Typed Pixels, Converted Through [R, G, B, A] At 16bit
+// Gray From Color Is The Rounded Mean Of R, G And B, No Alpha Is Fully Opaque
+// 8bit Samples Widen As v * 257 And Narrow With Rounding
+// ********************************************************
+pub trait Pixel: Copy {
+	fn from_rgba16(rgba: [u16; 4]) -> Self;
+	fn to_rgba16(self) -> [u16; 4];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgba16 {
+	pub r: u16,
+	pub g: u16,
+	pub b: u16,
+	pub a: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb16 {
+	pub r: u16,
+	pub g: u16,
+	pub b: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LumaA16 {
+	pub l: u16,
+	pub a: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Luma16 {
+	pub l: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgba8 {
+	pub r: u8,
+	pub g: u8,
+	pub b: u8,
+	pub a: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb8 {
+	pub r: u8,
+	pub g: u8,
+	pub b: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LumaA8 {
+	pub l: u8,
+	pub a: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Luma8 {
+	pub l: u8,
+}
+
+pub(crate) fn get_gray(rgba: [u16; 4]) -> u16 {
+	((rgba[0] as u32 + rgba[1] as u32 + rgba[2] as u32 + 1) / 3) as u16
+}
+
+fn get_8bit(sample: u16) -> u8 {
+	((sample as u32 * 255 + 32767) / 65535) as u8
+}
+
+fn get_16bit(sample: u8) -> u16 {
+	sample as u16 * 257
+}
+
+impl Pixel for Rgba16 {
+	fn from_rgba16(rgba: [u16; 4]) -> Rgba16 {
+		Rgba16 { r: rgba[0], g: rgba[1], b: rgba[2], a: rgba[3] }
+	}
+
+	fn to_rgba16(self) -> [u16; 4] {
+		[self.r, self.g, self.b, self.a]
+	}
+}
+
+impl Pixel for Rgb16 {
+	fn from_rgba16(rgba: [u16; 4]) -> Rgb16 {
+		Rgb16 { r: rgba[0], g: rgba[1], b: rgba[2] }
+	}
+
+	fn to_rgba16(self) -> [u16; 4] {
+		[self.r, self.g, self.b, 0xFFFF]
+	}
+}
+
+impl Pixel for LumaA16 {
+	fn from_rgba16(rgba: [u16; 4]) -> LumaA16 {
+		LumaA16 { l: get_gray(rgba), a: rgba[3] }
+	}
+
+	fn to_rgba16(self) -> [u16; 4] {
+		[self.l, self.l, self.l, self.a]
+	}
+}
+
+impl Pixel for Luma16 {
+	fn from_rgba16(rgba: [u16; 4]) -> Luma16 {
+		Luma16 { l: get_gray(rgba) }
+	}
+
+	fn to_rgba16(self) -> [u16; 4] {
+		[self.l, self.l, self.l, 0xFFFF]
+	}
+}
+
+impl Pixel for Rgba8 {
+	fn from_rgba16(rgba: [u16; 4]) -> Rgba8 {
+		Rgba8 { r: get_8bit(rgba[0]), g: get_8bit(rgba[1]), b: get_8bit(rgba[2]), a: get_8bit(rgba[3]) }
+	}
+
+	fn to_rgba16(self) -> [u16; 4] {
+		[get_16bit(self.r), get_16bit(self.g), get_16bit(self.b), get_16bit(self.a)]
+	}
+}
+
+impl Pixel for Rgb8 {
+	fn from_rgba16(rgba: [u16; 4]) -> Rgb8 {
+		Rgb8 { r: get_8bit(rgba[0]), g: get_8bit(rgba[1]), b: get_8bit(rgba[2]) }
+	}
+
+	fn to_rgba16(self) -> [u16; 4] {
+		[get_16bit(self.r), get_16bit(self.g), get_16bit(self.b), 0xFFFF]
+	}
+}
+
+impl Pixel for LumaA8 {
+	fn from_rgba16(rgba: [u16; 4]) -> LumaA8 {
+		LumaA8 { l: get_8bit(get_gray(rgba)), a: get_8bit(rgba[3]) }
+	}
+
+	fn to_rgba16(self) -> [u16; 4] {
+		let l = get_16bit(self.l);
+		[l, l, l, get_16bit(self.a)]
+	}
+}
+
+impl Pixel for Luma8 {
+	fn from_rgba16(rgba: [u16; 4]) -> Luma8 {
+		Luma8 { l: get_8bit(get_gray(rgba)) }
+	}
+
+	fn to_rgba16(self) -> [u16; 4] {
+		let l = get_16bit(self.l);
+		[l, l, l, 0xFFFF]
+	}
+}
+
+// ********************************************************
+// Pixels Left To Right, Top To Bottom, Over Rows y..end_y
+// ********************************************************
+pub struct Pixels<'a, P> {
+	image: &'a ImageBuffer,
+	x: u32,
+	y: u32,
+	end_y: u32,
+	pixel: PhantomData<P>,
+}
+
+impl<'a, P: Pixel> Pixels<'a, P> {
+	pub(crate) fn new(image: &'a ImageBuffer, y: u32, end_y: u32) -> Pixels<'a, P> {
+		Pixels { image, x: 0, y, end_y, pixel: PhantomData }
+	}
+}
+
+impl<'a, P: Pixel> Iterator for Pixels<'a, P> {
+	type Item = P;
+
+	fn next(&mut self) -> Option<P> {
+		if self.y >= self.end_y || self.image.width == 0 {
+			return None;
+		}
+		let pixel = P::from_rgba16(self.image.get_rgba(self.x, self.y));
+		self.x += 1;
+		if self.x == self.image.width {
+			self.x = 0;
+			self.y += 1;
+		}
+		Some(pixel)
+	}
+}
+
+// Each Row Is Its Own Pixels Iterator
+pub struct Rows<'a, P> {
+	image: &'a ImageBuffer,
+	y: u32,
+	pixel: PhantomData<P>,
+}
+
+impl<'a, P: Pixel> Rows<'a, P> {
+	pub(crate) fn new(image: &'a ImageBuffer) -> Rows<'a, P> {
+		Rows { image, y: 0, pixel: PhantomData }
+	}
+}
+
+impl<'a, P: Pixel> Iterator for Rows<'a, P> {
+	type Item = Pixels<'a, P>;
+
+	fn next(&mut self) -> Option<Pixels<'a, P>> {
+		if self.y >= self.image.height {
+			return None;
+		}
+		self.y += 1;
+		Some(Pixels::new(self.image, self.y - 1, self.y))
+	}
+}
@@ -0,0 +1,510 @@
+use byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
+use crc::crc32::{self, Hasher32};
+use flate2::read::ZlibDecoder;
+use std::cmp;
+use std::io;
+use std::io::prelude::*;
+use std::mem;
+
+use error::{Error, Result};
+use ffi;
+use filter::{self, FilterType};
+use image::ChannelLayout;
+use metadata::{self, Metadata};
+use pixel::{Pixel, Rgba16};
+use zlib::Deflater;
+use super::{PNG, PNG_IHDR, DecodeOptions, EncodeOptions, PositionReader, DEPTH_8, DEPTH_16, MAX_DIMENSION, COLOR_TYPE_RGB, COLOR_TYPE_RGBA, COLOR_TYPE_PALETTE};
+use super::{get_chunk, get_header_chunks, get_encode_ihdr, get_encode_header, get_encode_trailer, push_pixel};
+use super::{parse_ihdr, get_header, get_trailer, check_crc_sum, get_channels, get_bytes_per_pixel, get_row_bytes};
+use super::{get_raw_samples, get_samples, get_rgb_a, get_palette, get_palette_samples, HEADER_OFFSET};
+
+// ********************************************************
+// The Data Of Consecutive IDAT Chunks As One Stream
+// CRCs Are Checked As Each Chunk Ends, Errors Are Kept In error
+// Because The Inflater Only Passes io::Error Through
+// ********************************************************
+struct IdatReader<'a> {
+	img: PositionReader<'a>,
+	options: DecodeOptions,
+	// Data Bytes Left In The Current IDAT, Its Offset And Its CRC So Far
+	remaining: u64,
+	offset: u64,
+	digest: crc32::Digest,
+	// Tag Of The Chunk After The Last IDAT, 0 Until It Is Reached
+	next_tag: u64,
+	warnings: Vec<Error>,
+	error: Option<Error>,
+}
+
+impl<'a> IdatReader<'a> {
+	fn start_chunk(&mut self, idat_tag: u64) {
+		self.remaining = idat_tag >> 32;
+		self.offset = self.img.offset - 8;
+		self.digest = crc32::Digest::new(crc32::IEEE);
+		self.digest.write(&[0x49, 0x44, 0x41, 0x54]);
+	}
+
+	fn get_data(&mut self, buf: &mut [u8]) -> Result<usize> {
+		loop {
+			if self.next_tag != 0 {
+				return Ok(0);
+			}
+			if self.remaining > 0 {
+				let len = cmp::min(buf.len() as u64, self.remaining) as usize;
+				let n = match self.img.read(&mut buf[..len]) {
+					Ok(n) => n,
+					Err(e) => return Err(Error::from_io(e, ffi::IDAT, self.offset)),
+				};
+				if n == 0 {
+					return Err(Error::Truncated { chunk: ffi::IDAT, offset: self.offset });
+				}
+				self.digest.write(&buf[..n]);
+				self.remaining -= n as u64;
+				return Ok(n);
+			}
+
+			// End Of The Current Chunk: CRC, Then The Next Tag
+			match self.img.read_u32::<BigEndian>() {
+				Ok(crc) => match check_crc_sum(ffi::IDAT, crc, self.digest.sum32(), self.offset, &self.options, &mut self.warnings) {
+					Ok(_) => (),
+					Err(e) => return Err(e),
+				},
+				Err(e) => return Err(Error::from_io(e, ffi::IDAT, self.offset)),
+			};
+			let chunk_tag = match self.img.read_u64::<BigEndian>() {
+				Ok(chunk_tag) => chunk_tag,
+				Err(e) => return Err(Error::from_io(e, 0, self.img.offset)),
+			};
+			if chunk_tag as u32 == ffi::IDAT {
+				self.start_chunk(chunk_tag);
+			} else {
+				self.next_tag = chunk_tag;
+			}
+		}
+	}
+}
+
+impl<'a> Read for IdatReader<'a> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match self.get_data(buf) {
+			Ok(n) => Ok(n),
+			Err(e) => {
+				let error = io::Error::other(e.to_string());
+				self.error = Some(e);
+				Err(error)
+			},
+		}
+	}
+}
+
+// ********************************************************
+// Decodes One Scanline At A Time, Memory Stays At Two Rows
+// Plus The Inflater Buffers Whatever The Image Size
+// Only Non Interlaced Images, Adam7 Needs Every Pass Before A Full Row Exists
+// DecodeLimits: Width, Height And Metadata Bytes Apply,
+// max_bytes Bounds The Two Row Buffers, max_pixels Does Not Apply
+// ********************************************************
+pub struct RowDecoder<'a> {
+	pub ihdr: PNG_IHDR,
+	pub header: Vec<u8>,
+	// Filled By finish()
+	pub trailer: Vec<u8>,
+	pub metadata: Metadata,
+	pub warnings: Vec<Error>,
+	options: DecodeOptions,
+	// Inflated Metadata Bytes Still Allowed, Carried From The Header To The Trailer
+	inflate_budget: u64,
+	// PLTE/tRNS Expanded Once By with_options, Palette Images Only
+	palette: Option<(ChannelLayout, Vec<u16>)>,
+	inflater: ZlibDecoder<IdatReader<'a>>,
+	// Filter Type Byte Followed By The Scanline
+	row: Vec<u8>,
+	prev: Vec<u8>,
+	rows: u32,
+	bpp: usize,
+	idat_offset: u64,
+}
+
+impl<'a> RowDecoder<'a> {
+	pub fn new<R: Read + 'a>(reader: R) -> Result<RowDecoder<'a>> {
+		RowDecoder::with_options(reader, &DecodeOptions::default())
+	}
+
+	// Reads Everything Up To The First IDAT
+	pub fn with_options<R: Read + 'a>(reader: R, options: &DecodeOptions) -> Result<RowDecoder<'a>> {
+		let mut warnings = vec![];
+		let mut img = PositionReader { reader: Box::new(reader), offset: 0 };
+
+		let ihdr = match parse_ihdr(&mut img, options, &mut warnings) {
+			Ok(ihdr) => ihdr,
+			Err(e) => return Err(e),
+		};
+		if ihdr.interlace != 0 {
+			return Err(Error::Unsupported { chunk: ffi::IHDR, offset: 28, feature: "Interlaced Row Decoding" });
+		}
+		let channels = get_channels(ihdr.color_type);
+		let row_bytes = get_row_bytes(ihdr.width, channels, ihdr.depth);
+		if 2 * (row_bytes as u64 + 1) > options.limits.max_bytes {
+			return Err(Error::LimitExceeded { chunk: ffi::IHDR, offset: 16, limit: "Bytes Allocated" });
+		}
+
+		let (header, idat_tag) = match get_header(&mut img, options, &mut warnings) {
+			Ok(header) => header,
+			Err(e) => return Err(e),
+		};
+		let mut metadata = Metadata::default();
+		let mut inflate_budget = options.limits.max_metadata_bytes;
+		let header = match metadata::get_metadata(&header, HEADER_OFFSET, &ihdr, &mut inflate_budget, &mut metadata, false, &mut warnings) {
+			Ok(header) => header,
+			Err(e) => return Err(e),
+		};
+
+		let idat_offset = img.offset - 8;
+		let palette = if ihdr.color_type == COLOR_TYPE_PALETTE {
+			match get_palette(&ihdr, &header, idat_offset) {
+				Ok(palette) => Some(palette),
+				Err(e) => return Err(e),
+			}
+		} else {
+			None
+		};
+		let mut idat = IdatReader {
+			img,
+			options: options.clone(),
+			remaining: 0,
+			offset: 0,
+			digest: crc32::Digest::new(crc32::IEEE),
+			next_tag: 0,
+			warnings: vec![],
+			error: None,
+		};
+		idat.start_chunk(idat_tag);
+
+		Ok(RowDecoder {
+			bpp: get_bytes_per_pixel(channels, ihdr.depth),
+			ihdr,
+			header,
+			trailer: vec![],
+			metadata,
+			warnings,
+			options: options.clone(),
+			inflate_budget,
+			palette,
+			inflater: ZlibDecoder::new(idat),
+			row: vec![0; row_bytes + 1],
+			prev: vec![0; row_bytes + 1],
+			rows: 0,
+			idat_offset,
+		})
+	}
+
+	// Errors Raised While Reading IDAT Chunks Win Over The Inflater's Own
+	fn get_inflate_error(&mut self, error: io::Error) -> Error {
+		match self.inflater.get_mut().error.take() {
+			Some(e) => e,
+			None if error.kind() == io::ErrorKind::UnexpectedEof => Error::Truncated { chunk: ffi::IDAT, offset: self.idat_offset },
+			None => Error::Zlib { chunk: ffi::IDAT, offset: self.idat_offset, message: error.to_string() },
+		}
+	}
+
+	// ********************************************************
+	// The Next Defiltered Scanline, Samples Packed As In The File
+	// None After The Last Row
+	// ********************************************************
+	pub fn next_row(&mut self) -> Result<Option<&[u8]>> {
+		if self.rows == self.ihdr.height {
+			return Ok(None);
+		}
+
+		// The Row Just Returned Becomes The Row Above
+		mem::swap(&mut self.row, &mut self.prev);
+		let read = self.inflater.read_exact(&mut self.row);
+		// CRC Warnings Of Every IDAT Read So Far, Even If The Caller Stops Early
+		self.warnings.append(&mut self.inflater.get_mut().warnings);
+		match read {
+			Ok(_) => (),
+			Err(e) => return Err(self.get_inflate_error(e)),
+		};
+		let filter_type = match FilterType::from_u8(self.row[0]) {
+			Some(filter_type) => filter_type,
+			None => return Err(Error::InvalidChunk { chunk: ffi::IDAT, offset: self.idat_offset, reason: "Invalid Filter Type" }),
+		};
+
+		// The First Row Has No Previous Row, unfilter_row Treats It As Zeros
+		let prev: &[u8] = if self.rows == 0 { &[] } else { &self.prev[1..] };
+		filter::unfilter_row(filter_type, self.bpp, prev, &mut self.row[1..]);
+		self.rows += 1;
+		Ok(Some(&self.row[1..]))
+	}
+
+	// ********************************************************
+	// The Next Row Widened To 16bit And Appended Like PNG.rgb()/PNG.alpha()
+	// Returns false After The Last Row
+	// ********************************************************
+	pub fn next_rgb_a(&mut self, rgb: &mut Vec<u16>, alpha: &mut Vec<u16>) -> Result<bool> {
+		match self.next_row() {
+			Ok(Some(_)) => (),
+			Ok(None) => return Ok(false),
+			Err(e) => return Err(e),
+		};
+
+		let channels = get_channels(self.ihdr.color_type);
+		let raw = get_raw_samples(&self.row[1..], self.ihdr.width, self.ihdr.depth, channels);
+		if let Some((layout, ref palette)) = self.palette {
+			match get_palette_samples(&raw, layout, palette, self.idat_offset) {
+				Ok(samples) => get_rgb_a(&samples, layout.channels(), rgb, alpha),
+				Err(e) => Err(e),
+			}
+		} else {
+			get_rgb_a(&get_samples(raw, self.ihdr.depth), channels, rgb, alpha)
+		}
+	}
+
+	// ********************************************************
+	// Skips Any Rows Not Read Yet, Then Reads The Chunks After IDAT Up To IEND
+	// Into trailer And metadata
+	// ********************************************************
+	pub fn finish(&mut self) -> Result<bool> {
+		let idat = self.inflater.get_mut();
+		match io::copy(idat, &mut io::sink()) {
+			Ok(_) => (),
+			Err(e) => return Err(match idat.error.take() {
+				Some(error) => error,
+				None => Error::from_io(e, ffi::IDAT, idat.offset),
+			}),
+		};
+		self.warnings.append(&mut idat.warnings);
+
+		let trailer_offset = idat.img.offset - 8;
+		let trailer = match get_trailer(&mut idat.img, idat.next_tag, self.header.len() as u64, &self.options, &mut self.warnings) {
+			Ok(trailer) => trailer,
+			Err(e) => return Err(e),
+		};
+		self.trailer = match metadata::get_metadata(&trailer, trailer_offset, &self.ihdr, &mut self.inflate_budget, &mut self.metadata, true, &mut self.warnings) {
+			Ok(trailer) => trailer,
+			Err(e) => return Err(e),
+		};
+		Ok(true)
+	}
+}
+
+// ********************************************************
+// Encodes One Scanline At A Time, Each Row Is Filtered Against The Previous One
+// And Deflated Straight Away, IDAT Chunks Are Written As They Reach MAX_IDAT_SIZE
+// png Supplies ihdr, header, trailer And metadata, Its image Is Not Used
+// The Color Type Is png.ihdr.color_type, Palette Images Are Written As RGB, Or RGBA With tRNS
+// ********************************************************
+pub struct RowEncoder<W: Write> {
+	writer: W,
+	width: u32,
+	height: u32,
+	depth: u8,
+	color_type: u8,
+	filter: filter::FilterStrategy,
+	deflater: Deflater,
+	// Deflated Bytes Not Yet Written As An IDAT Chunk
+	pending: Vec<u8>,
+	// Chunks After IDAT, Built Up Front So png Is Not Borrowed
+	trailer: Vec<u8>,
+	filterd: Vec<u8>,
+	prev: Vec<u8>,
+	rows: u32,
+	row_bytes: usize,
+	bpp: usize,
+}
+
+impl<W: Write> RowEncoder<W> {
+	// Writes The Signature, IHDR And Every Chunk Before IDAT
+	pub fn new(mut writer: W, png: &PNG, depth: u8, options: &EncodeOptions) -> Result<RowEncoder<W>> {
+		if depth != DEPTH_8 && depth != DEPTH_16 {
+			return Err(Error::Unsupported { chunk: ffi::IHDR, offset: 24, feature: "Bit Depth" });
+		}
+		if png.ihdr.width == 0 || png.ihdr.width > MAX_DIMENSION {
+			return Err(Error::InvalidHeader { chunk: ffi::IHDR, offset: 16, field: "Width" });
+		}
+		if png.ihdr.height == 0 || png.ihdr.height > MAX_DIMENSION {
+			return Err(Error::InvalidHeader { chunk: ffi::IHDR, offset: 20, field: "Height" });
+		}
+		if png.ihdr.interlace != 0 {
+			return Err(Error::Unsupported { chunk: ffi::IHDR, offset: 28, feature: "Interlaced Row Encoding" });
+		}
+		let color_type = match png.ihdr.color_type {
+			// tRNS Gives Palette Entries Alpha, Which Only RGBA Keeps
+			COLOR_TYPE_PALETTE if get_header_chunks(&png.header).iter().any(|&(chunk_type, _)| chunk_type == ffi::tRNS) => COLOR_TYPE_RGBA,
+			COLOR_TYPE_PALETTE => COLOR_TYPE_RGB,
+			color_type if [0, 2, 4, 6].contains(&color_type) => color_type,
+			_ => return Err(Error::InvalidHeader { chunk: ffi::IHDR, offset: 25, field: "Color Type" }),
+		};
+		let deflater = match Deflater::new(options.level, options.zlib_strategy) {
+			Ok(deflater) => deflater,
+			Err(e) => return Err(e),
+		};
+		let header = match get_encode_header(png, depth, color_type, options) {
+			Ok(header) => header,
+			Err(e) => return Err(e),
+		};
+		let trailer = match get_encode_trailer(png, depth, color_type, options) {
+			Ok(trailer) => trailer,
+			Err(e) => return Err(e),
+		};
+
+		match writer.write_u64::<BigEndian>(ffi::PNG_SIG) {
+			Ok(_) => (),
+			Err(e) => return Err(Error::from(e)),
+		};
+		match writer.write_all(&get_encode_ihdr(&png.ihdr, depth, color_type, 0)) {
+			Ok(_) => (),
+			Err(e) => return Err(Error::from(e)),
+		};
+		match writer.write_all(&header) {
+			Ok(_) => (),
+			Err(e) => return Err(Error::from(e)),
+		};
+
+		let channels = get_channels(color_type);
+		Ok(RowEncoder {
+			writer,
+			width: png.ihdr.width,
+			height: png.ihdr.height,
+			depth,
+			color_type,
+			filter: options.filter,
+			deflater,
+			pending: vec![],
+			trailer,
+			filterd: vec![],
+			prev: vec![],
+			rows: 0,
+			row_bytes: get_row_bytes(png.ihdr.width, channels, depth),
+			bpp: get_bytes_per_pixel(channels, depth),
+		})
+	}
+
+	pub fn color_type(&self) -> u8 {
+		self.color_type
+	}
+
+	// Only Whole IDAT Chunks Unless Ending The Stream
+	fn write_idat(&mut self, all: bool) -> Result<bool> {
+		let mut written = 0;
+		while self.pending.len() - written >= ffi::MAX_IDAT_SIZE || (all && written < self.pending.len()) {
+			let end = cmp::min(written + ffi::MAX_IDAT_SIZE, self.pending.len());
+			match self.writer.write_all(&get_chunk(ffi::IDAT, &self.pending[written..end])) {
+				Ok(_) => (),
+				Err(e) => return Err(Error::from(e)),
+			};
+			written = end;
+		}
+		self.pending.drain(..written);
+		Ok(true)
+	}
+
+	// ********************************************************
+	// One Unfiltered Scanline In The Output Format:
+	// color_type() Samples At depth, Big Endian For 16bit
+	// ********************************************************
+	pub fn write_row(&mut self, row: &[u8]) -> Result<bool> {
+		if self.rows == self.height {
+			return Err(Error::InvalidImage { reason: "More Rows Than Height" });
+		}
+		if row.len() != self.row_bytes {
+			return Err(Error::InvalidImage { reason: "Row Length" });
+		}
+
+		self.filterd.clear();
+		match filter::filter_row_with_strategy(self.filter, self.bpp, &self.prev, row, &mut self.filterd) {
+			Ok(_) => (),
+			Err(e) => return Err(e),
+		};
+		match self.deflater.write(&self.filterd, &mut self.pending) {
+			Ok(_) => (),
+			Err(e) => return Err(e),
+		};
+		self.prev.clear();
+		self.prev.extend_from_slice(row);
+		self.rows += 1;
+		self.write_idat(false)
+	}
+
+	// ********************************************************
+	// Pixels Are Only Written When color_type() Holds Them Exactly:
+	// Gray Needs R == G == B, No Alpha Channel Needs Full Opacity
+	// ********************************************************
+	fn push_exact_pixel(&self, row: &mut Vec<u8>, pixel: Rgba16) -> Result<bool> {
+		if self.color_type & 0x02 == 0 && (pixel.r != pixel.g || pixel.g != pixel.b) {
+			return Err(Error::InvalidImage { reason: "Color Pixel In Gray Row" });
+		}
+		if self.color_type & 0x04 == 0 && pixel.a != 0xFFFF {
+			return Err(Error::InvalidImage { reason: "Translucent Pixel Without Alpha" });
+		}
+		push_pixel(row, pixel, self.depth, self.color_type);
+		Ok(true)
+	}
+
+	// One Row Laid Out Like PNG.rgb()/PNG.alpha(), width Pixels Each
+	pub fn write_rgb_a(&mut self, rgb: &[u16], alpha: &[u16]) -> Result<bool> {
+		let width = self.width as usize;
+		if rgb.len() != 3 * width || alpha.len() != width {
+			return Err(Error::InvalidImage { reason: "Row Length" });
+		}
+		let mut row = Vec::<u8>::with_capacity(self.row_bytes);
+		for j in 0..width {
+			match self.push_exact_pixel(&mut row, Rgba16 { r: rgb[3 * j], g: rgb[3 * j + 1], b: rgb[3 * j + 2], a: alpha[j] }) {
+				Ok(_) => (),
+				Err(e) => return Err(e),
+			};
+		}
+		self.write_row(&row)
+	}
+
+	// One Row Of width Typed Pixels, Any Pixel Type color_type() Holds Exactly
+	pub fn write_pixels<P: Pixel>(&mut self, pixels: &[P]) -> Result<bool> {
+		if pixels.len() != self.width as usize {
+			return Err(Error::InvalidImage { reason: "Row Length" });
+		}
+		let mut row = Vec::<u8>::with_capacity(self.row_bytes);
+		for &pixel in pixels {
+			match self.push_exact_pixel(&mut row, Rgba16::from_rgba16(pixel.to_rgba16())) {
+				Ok(_) => (),
+				Err(e) => return Err(e),
+			};
+		}
+		self.write_row(&row)
+	}
+
+	// ********************************************************
+	// Ends The zlib Stream, Writes The Last IDAT, The Trailer And IEND
+	// Every Row Has To Be Written First
+	// ********************************************************
+	pub fn finish(mut self) -> Result<W> {
+		if self.rows != self.height {
+			return Err(Error::InvalidImage { reason: "Fewer Rows Than Height" });
+		}
+		match self.deflater.finish(&mut self.pending) {
+			Ok(_) => (),
+			Err(e) => return Err(e),
+		};
+		match self.write_idat(true) {
+			Ok(_) => (),
+			Err(e) => return Err(e),
+		};
+
+		match self.writer.write_all(&self.trailer) {
+			Ok(_) => (),
+			Err(e) => return Err(Error::from(e)),
+		};
+		match self.writer.write_u64::<BigEndian>(ffi::IEND) {
+			Ok(_) => (),
+			Err(e) => return Err(Error::from(e)),
+		};
+		match self.writer.write_u32::<BigEndian>(ffi::TAIL) {
+			Ok(_) => (),
+			Err(e) => return Err(Error::from(e)),
+		};
+		match self.writer.flush() {
+			Ok(_) => (),
+			Err(e) => return Err(Error::from(e)),
+		};
+		Ok(self.writer)
+	}
+}
@@ -0,0 +1,176 @@
+use std::str;
+
+use error::Result;
+use ffi;
+use super::get_chunk;
+use metadata::{get_compressed, get_inflated, get_latin1, get_latin1_bytes};
+
+// ********************************************************
+// tEXt: Latin-1, zTXt: Latin-1 Compressed, iTXt: UTF-8 With Language Tags
+// ********************************************************
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextKind {
+	Text,
+	Compressed,
+	International { compressed: bool, language: String, translated_keyword: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextChunk {
+	pub keyword: String,
+	pub text: String,
+	pub kind: TextKind,
+	// Found After IDAT And Written Back There
+	pub after_idat: bool,
+}
+
+impl TextChunk {
+	// tEXt When The Text Fits Latin-1, Otherwise iTXt
+	pub fn new(keyword: &str, text: &str) -> TextChunk {
+		TextChunk { keyword: keyword.to_string(), text: text.to_string(), kind: get_text_kind(TextKind::Text, text), after_idat: false }
+	}
+
+	pub fn compressed(keyword: &str, text: &str) -> TextChunk {
+		TextChunk { keyword: keyword.to_string(), text: text.to_string(), kind: TextKind::Compressed, after_idat: false }
+	}
+
+	pub fn international(keyword: &str, text: &str, language: &str, translated_keyword: &str) -> TextChunk {
+		TextChunk {
+			keyword: keyword.to_string(),
+			text: text.to_string(),
+			kind: TextKind::International { compressed: false, language: language.to_string(), translated_keyword: translated_keyword.to_string() },
+			after_idat: false,
+		}
+	}
+}
+
+pub(crate) fn is_latin1(text: &str) -> bool {
+	text.chars().all(|c| (c as u32) < 0x100)
+}
+
+// tEXt And zTXt Only Hold Latin-1, Other Text Needs iTXt (Compressed For zTXt)
+pub(crate) fn get_text_kind(kind: TextKind, text: &str) -> TextKind {
+	if is_latin1(text) {
+		return kind;
+	}
+	match kind {
+		TextKind::Text => TextKind::International { compressed: false, language: String::new(), translated_keyword: String::new() },
+		TextKind::Compressed => TextKind::International { compressed: true, language: String::new(), translated_keyword: String::new() },
+		kind => kind,
+	}
+}
+
+// ********************************************************
+// Keywords Are 1-79 Printable Latin-1 Characters,
+// No Leading, Trailing Or Consecutive Spaces
+// ********************************************************
+pub(crate) fn is_valid_keyword(keyword: &str) -> bool {
+	let count = keyword.chars().count();
+	count > 0 && count < 80 &&
+		keyword.chars().all(|c| (' '..='~').contains(&c) || ('\u{A1}'..='\u{FF}').contains(&c)) &&
+		!keyword.starts_with(' ') && !keyword.ends_with(' ') && !keyword.contains("  ")
+}
+
+// ********************************************************
+// None When The Chunk Is Malformed
+// ********************************************************
+pub(crate) fn get_text_chunk(chunk_type: u32, data: &[u8], offset: u64, inflate_budget: &mut u64) -> Result<Option<TextChunk>> {
+	let keyword_len = match data.iter().position(|&b| b == 0) {
+		Some(keyword_len) => keyword_len,
+		None => return Ok(None),
+	};
+	let keyword = get_latin1(&data[..keyword_len]);
+	if !is_valid_keyword(&keyword) {
+		return Ok(None);
+	}
+	let data = &data[keyword_len + 1..];
+
+	match chunk_type {
+		ffi::tEXt => Ok(Some(TextChunk { keyword, text: get_latin1(data), kind: TextKind::Text, after_idat: false })),
+		ffi::zTXt => {
+			if data.is_empty() || data[0] != 0 {
+				return Ok(None);
+			}
+			match get_inflated(&data[1..], chunk_type, offset, inflate_budget) {
+				Ok(Some(text)) => Ok(Some(TextChunk { keyword, text: get_latin1(&text), kind: TextKind::Compressed, after_idat: false })),
+				Ok(None) => Ok(None),
+				Err(e) => Err(e),
+			}
+		},
+		_ => {
+			// Compression Flag, Compression Method, Language\0, Translated Keyword\0, Text
+			if data.len() < 2 || data[0] > 1 || data[1] != 0 {
+				return Ok(None);
+			}
+			let compressed = data[0] == 1;
+			let mut fields = data[2..].splitn(3, |&b| b == 0);
+			let (language, translated_keyword, text) = match (fields.next(), fields.next(), fields.next()) {
+				(Some(language), Some(translated_keyword), Some(text)) => (language, translated_keyword, text),
+				_ => return Ok(None),
+			};
+
+			let text = if compressed {
+				match get_inflated(text, chunk_type, offset, inflate_budget) {
+					Ok(Some(text)) => text,
+					Ok(None) => return Ok(None),
+					Err(e) => return Err(e),
+				}
+			} else {
+				text.to_vec()
+			};
+			let (language, translated_keyword, text) = match (str::from_utf8(language), str::from_utf8(translated_keyword), String::from_utf8(text)) {
+				(Ok(language), Ok(translated_keyword), Ok(text)) => (language.to_string(), translated_keyword.to_string(), text),
+				_ => return Ok(None),
+			};
+			Ok(Some(TextChunk { keyword, text, kind: TextKind::International { compressed, language, translated_keyword }, after_idat: false }))
+		},
+	}
+}
+
+pub(crate) fn get_chunk_type(chunk: &TextChunk) -> u32 {
+	match chunk.kind {
+		TextKind::Text => ffi::tEXt,
+		TextKind::Compressed => ffi::zTXt,
+		TextKind::International { .. } => ffi::iTXt,
+	}
+}
+
+pub(crate) fn get_text_chunks(text: &[TextChunk], after_idat: bool) -> Result<Vec<u8>> {
+	let mut chunks = Vec::<u8>::new();
+	for chunk in text.iter().filter(|chunk| chunk.after_idat == after_idat) {
+		let mut data = get_latin1_bytes(&chunk.keyword);
+		data.push(0);
+		let chunk_type = match chunk.kind {
+			TextKind::Text => {
+				data.extend_from_slice(&get_latin1_bytes(&chunk.text));
+				ffi::tEXt
+			},
+			TextKind::Compressed => {
+				data.push(0);
+				match get_compressed(&get_latin1_bytes(&chunk.text), ffi::zTXt) {
+					Ok(text) => data.extend_from_slice(&text),
+					Err(e) => return Err(e),
+				};
+				ffi::zTXt
+			},
+			TextKind::International { compressed, ref language, ref translated_keyword } => {
+				data.extend_from_slice(&[compressed as u8, 0]);
+				data.extend_from_slice(language.as_bytes());
+				data.push(0);
+				data.extend_from_slice(translated_keyword.as_bytes());
+				data.push(0);
+				if compressed {
+					match get_compressed(chunk.text.as_bytes(), ffi::iTXt) {
+						Ok(text) => data.extend_from_slice(&text),
+						Err(e) => return Err(e),
+					};
+				} else {
+					data.extend_from_slice(chunk.text.as_bytes());
+				}
+				ffi::iTXt
+			},
+		};
+		chunks.extend_from_slice(&get_chunk(chunk_type, &data));
+	}
+	Ok(chunks)
+}
@@ -0,0 +1,117 @@
+use miniz_sys as mz;
+use std::cmp;
+use std::mem;
+use std::os::raw::{c_int, c_uint};
+
+use error::{Error, Result};
+use ffi;
+
+pub(crate) const MAX_LEVEL: u8 = 9;
+
+// ********************************************************
+// zlib Deflate Strategies, Values Match Z_FILTERED, ...
+// ********************************************************
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ZlibStrategy {
+	#[default]
+	Default = 0,
+	// Favors Huffman Coding Over String Matching, Tuned For Filtered Image Data
+	Filtered = 1,
+	// No String Matching At All
+	HuffmanOnly = 2,
+	// Matches Only Runs Of The Previous Byte
+	Rle = 3,
+}
+
+// zlib's Default Memory Level
+const MEM_LEVEL: c_int = 8;
+const OUT_BUFFER_SIZE: usize = 1 << 16;
+// Input Is Fed In Pieces That Fit avail_in
+const MAX_INPUT: usize = 1 << 30;
+
+fn get_zlib_error(status: c_int, call: &str) -> Error {
+	Error::Zlib { chunk: ffi::IDAT, offset: 0, message: format!("{} Returned {}", call, status) }
+}
+
+// ********************************************************
+// A zlib Stream Fed In Pieces, Output Is Appended As miniz Produces It
+// ********************************************************
+pub(crate) struct Deflater {
+	// Boxed So The Stream Never Moves While miniz Holds It
+	stream: Box<mz::mz_stream>,
+	buffer: Vec<u8>,
+}
+
+impl Deflater {
+	pub(crate) fn new(level: u8, strategy: ZlibStrategy) -> Result<Deflater> {
+		if level > MAX_LEVEL {
+			return Err(Error::Unsupported { chunk: ffi::IDAT, offset: 0, feature: "Compression Level" });
+		}
+
+		// A Zeroed mz_stream Has No Custom Allocator, miniz Uses Its Own
+		let mut stream: Box<mz::mz_stream> = Box::new(unsafe { mem::zeroed() });
+		let status = unsafe { mz::mz_deflateInit2(&mut *stream, level as c_int, mz::MZ_DEFLATED, mz::MZ_DEFAULT_WINDOW_BITS, MEM_LEVEL, strategy as c_int) };
+		if status != mz::MZ_OK {
+			return Err(get_zlib_error(status, "deflateInit2"));
+		}
+		Ok(Deflater { stream, buffer: vec![0; OUT_BUFFER_SIZE] })
+	}
+
+	pub(crate) fn write(&mut self, data: &[u8], out: &mut Vec<u8>) -> Result<bool> {
+		self.deflate(data, mz::MZ_NO_FLUSH, out)
+	}
+
+	// Flushes Everything Buffered And Ends The Stream With Its Adler-32
+	pub(crate) fn finish(&mut self, out: &mut Vec<u8>) -> Result<bool> {
+		self.deflate(&[], mz::MZ_FINISH, out)
+	}
+
+	fn deflate(&mut self, data: &[u8], flush: c_int, out: &mut Vec<u8>) -> Result<bool> {
+		let mut consumed = 0;
+		loop {
+			let available = cmp::min(data.len() - consumed, MAX_INPUT);
+			let last = consumed + available == data.len();
+			self.stream.next_in = data[consumed..].as_ptr();
+			self.stream.avail_in = available as c_uint;
+			self.stream.next_out = self.buffer.as_mut_ptr();
+			self.stream.avail_out = self.buffer.len() as c_uint;
+
+			let status = unsafe { mz::mz_deflate(&mut *self.stream, if last { flush } else { mz::MZ_NO_FLUSH }) };
+			consumed += available - self.stream.avail_in as usize;
+			let produced = self.buffer.len() - self.stream.avail_out as usize;
+			out.extend_from_slice(&self.buffer[..produced]);
+
+			let done = consumed == data.len();
+			match status {
+				mz::MZ_STREAM_END => return Ok(true),
+				// Without A Flush The Input Is Done Once It Is Consumed And Output Stopped Short Of The Buffer
+				mz::MZ_OK if flush == mz::MZ_NO_FLUSH && done && produced < self.buffer.len() => return Ok(true),
+				mz::MZ_OK => (),
+				// Nothing Left To Consume Or Emit
+				mz::MZ_BUF_ERROR if flush == mz::MZ_NO_FLUSH && done => return Ok(true),
+				_ => return Err(get_zlib_error(status, "deflate")),
+			}
+		}
+	}
+}
+
+impl Drop for Deflater {
+	fn drop(&mut self) {
+		unsafe { mz::mz_deflateEnd(&mut *self.stream) };
+	}
+}
+
+// ********************************************************
+// Deflates data Into A zlib Stream, level 0 Stores Uncompressed Blocks
+// ********************************************************
+pub(crate) fn get_deflated(data: &[u8], level: u8, strategy: ZlibStrategy) -> Result<Vec<u8>> {
+	let mut deflater = match Deflater::new(level, strategy) {
+		Ok(deflater) => deflater,
+		Err(e) => return Err(e),
+	};
+	let mut deflated = Vec::<u8>::with_capacity(data.len() / 2 + 64);
+	match deflater.deflate(data, mz::MZ_FINISH, &mut deflated) {
+		Ok(_) => Ok(deflated),
+		Err(e) => Err(e),
+	}
+}
@@ -7,14 +7,14 @@ fn test() {
 
 	let files: Vec<_> = ScanDir::files()
 		.read("./test_images", |iter| {
-			iter.filter(|&(_, ref name)| name.ends_with(".png"))
+			iter.filter(|(_, name)| name.ends_with(".png"))
 				.map(|(entry, _)| entry.path())
 				.collect()
 		})
 		.unwrap();
 
 	for f in files {
-		let mut png = match png16::decode_16bit_png(f.to_str().unwrap()) {
+		let png = match png16::decode_16bit_png(f.to_str().unwrap()) {
 			Ok(png) => png,
 			Err(e) => {
 				panic!("Error Decoding PNG: {:?}", e);
@@ -26,3 +26,1145 @@ fn test() {
 		};		
 	}
 }
+
+#[test]
+fn test_decode_8bit_rgba() {
+	let png = match png16::decode_16bit_png("./test_images/rgba_8bit.png") {
+		Ok(png) => png,
+		Err(e) => panic!("Error Decoding PNG: {:?}", e),
+	};
+	assert_eq!(png.ihdr.depth, png16::DEPTH_8);
+
+	let (width, height) = (png.ihdr.width as usize, png.ihdr.height as usize);
+	let (rgb, alpha) = (png.rgb(), png.alpha());
+	assert_eq!(rgb.len(), width * height * 3);
+	assert_eq!(alpha.len(), width * height);
+
+	for y in 0..height {
+		for x in 0..width {
+			let j = x + y * width;
+			let r = ((x * 31 + y * 7) & 0xFF) as u16;
+			let g = ((x * 5 + y * 13) & 0xFF) as u16;
+			let b = ((x * y) & 0xFF) as u16;
+			let a = ((255 - x * 3 - y) & 0xFF) as u16;
+			// 8bit Samples Widened To 16bit
+			assert_eq!(&rgb[3 * j..3 * j + 3], &[r * 257, g * 257, b * 257]);
+			assert_eq!(alpha[j], a * 257);
+		}
+	}
+}
+
+#[test]
+fn test_decode_rgb() {
+	let png = match png16::decode_16bit_png("./test_images/rgb_16bit.png") {
+		Ok(png) => png,
+		Err(e) => panic!("Error Decoding PNG: {:?}", e),
+	};
+	assert_eq!(png.ihdr.color_type, png16::COLOR_TYPE_RGB);
+
+	let width = png.ihdr.width as usize;
+	let rgb = png.rgb();
+	for y in 0..png.ihdr.height as usize {
+		for x in 0..width {
+			let j = x + y * width;
+			let r = ((x * 7919 + y * 104729) & 0xFFFF) as u16;
+			let g = ((x * 40503 + y * 211) & 0xFFFF) as u16;
+			let b = ((x * y * 977) & 0xFFFF) as u16;
+			assert_eq!(&rgb[3 * j..3 * j + 3], &[r, g, b]);
+		}
+	}
+	// No Alpha Channel Means Fully Opaque
+	assert!(png.alpha().iter().all(|&a| a == 0xFFFF));
+
+	let png = match png16::decode_16bit_png("./test_images/rgb_8bit.png") {
+		Ok(png) => png,
+		Err(e) => panic!("Error Decoding PNG: {:?}", e),
+	};
+	assert_eq!(png.rgb()[3 * 10..3 * 10 + 3], [(31 + 7) * 257, (5 + 13) * 257, 257]);
+}
+
+#[test]
+fn test_encode_rgb() {
+	let result = std::env::temp_dir().join("png16_encode_rgb.png");
+	let result = result.to_str().unwrap();
+
+	let mut png = png16::decode_16bit_png("./test_images/rgb_16bit.png").unwrap();
+	let expected = png.rgb();
+	png16::encode_png(png, png16::DEPTH_16, result).unwrap();
+
+	png = png16::decode_16bit_png(result).unwrap();
+	assert_eq!(png.ihdr.color_type, png16::COLOR_TYPE_RGB);
+	assert_eq!(png.rgb(), expected);
+
+	// Translucent Pixels Force RGBA Output
+	png.image = png.image.to_layout(png16::ChannelLayout::Rgba);
+	png.image.pixel_mut(0, 0)[3] = 0x8000;
+	png16::encode_png(png, png16::DEPTH_16, result).unwrap();
+
+	png = png16::decode_16bit_png(result).unwrap();
+	assert_eq!(png.ihdr.color_type, png16::COLOR_TYPE_RGBA);
+	assert_eq!(png.alpha()[0], 0x8000);
+
+	// An Entirely Opaque RGBA Source Is Written As RGB
+	png.image.pixel_mut(0, 0)[3] = 0xFFFF;
+	let mut out = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_16, &mut out).unwrap();
+	let decoded = png16::decode_16bit_png_from_bytes(&out).unwrap();
+	assert_eq!(decoded.ihdr.color_type, png16::COLOR_TYPE_RGB);
+	assert_eq!(decoded.rgb(), png.rgb());
+
+	let png = png16::PNG::from_rgba16(2, 1, vec![0x1234, 0x5678, 0x9ABC, 0xFFFF, 0, 0, 0, 0xFFFF]).unwrap();
+	let mut out = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_16, &mut out).unwrap();
+	assert_eq!(png16::decode_16bit_png_from_bytes(&out).unwrap().ihdr.color_type, png16::COLOR_TYPE_RGB);
+}
+
+#[test]
+fn test_decode_gray() {
+	for &depth in &[1, 2, 4, 8, 16] {
+		let max = (1 << depth) - 1;
+		let scale = 0xFFFF / max;
+		for &alpha in &[false, true] {
+			let path = match alpha {
+				false => format!("./test_images/gray_{}bit.png", depth),
+				true if depth >= 8 => format!("./test_images/gray_alpha_{}bit.png", depth),
+				true => continue,
+			};
+			let png = match png16::decode_16bit_png(&path) {
+				Ok(png) => png,
+				Err(e) => panic!("Error Decoding {}: {:?}", path, e),
+			};
+
+			let width = png.ihdr.width as usize;
+			let (rgb, alpha_plane) = (png.rgb(), png.alpha());
+			for y in 0..png.ihdr.height as usize {
+				for x in 0..width {
+					let j = x + y * width;
+					let v = (((x * 3 + y * 5) & max) * scale) as u16;
+					let a = if alpha { ((((x + y) * 37) & max) * scale) as u16 } else { 0xFFFF };
+					assert_eq!(&rgb[3 * j..3 * j + 3], &[v, v, v], "{} ({}, {})", path, x, y);
+					assert_eq!(alpha_plane[j], a, "{} ({}, {})", path, x, y);
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn test_encode_gray() {
+	let result = std::env::temp_dir().join("png16_encode_gray.png");
+	let result = result.to_str().unwrap();
+
+	for &(source, color_type) in &[("./test_images/gray_4bit.png", png16::COLOR_TYPE_GRAY), ("./test_images/gray_alpha_16bit.png", png16::COLOR_TYPE_GRAY_ALPHA)] {
+		let png = png16::decode_16bit_png(source).unwrap();
+		let (rgb, alpha) = (png.rgb(), png.alpha());
+		png16::encode_png(png, png16::DEPTH_16, result).unwrap();
+
+		let png = png16::decode_16bit_png(result).unwrap();
+		assert_eq!(png.ihdr.color_type, color_type);
+		assert_eq!(png.rgb(), rgb);
+		assert_eq!(png.alpha(), alpha);
+	}
+
+	// Non Gray Pixels Force RGB Output
+	let mut png = png16::decode_16bit_png("./test_images/gray_8bit.png").unwrap();
+	png.image = png.image.to_layout(png16::ChannelLayout::Rgb);
+	png.image.samples[0] = 0x1234;
+	png16::encode_png(png, png16::DEPTH_8, result).unwrap();
+	assert_eq!(png16::decode_16bit_png(result).unwrap().ihdr.color_type, png16::COLOR_TYPE_RGB);
+}
+
+#[test]
+fn test_decode_palette() {
+	for &depth in &[1, 2, 4, 8] {
+		let path = format!("./test_images/palette_{}bit.png", depth);
+		let png = match png16::decode_16bit_png(&path) {
+			Ok(png) => png,
+			Err(e) => panic!("Error Decoding {}: {:?}", path, e),
+		};
+		assert_eq!(png.ihdr.color_type, png16::COLOR_TYPE_PALETTE);
+
+		// PLTE Has 2^depth Entries (200 At 8bit), tRNS Covers The First Half
+		let entries = if depth == 8 { 200 } else { 1 << depth };
+		let width = png.ihdr.width as usize;
+		let (rgb, alpha) = (png.rgb(), png.alpha());
+		for y in 0..png.ihdr.height as usize {
+			for x in 0..width {
+				let j = x + y * width;
+				let i = if depth == 8 { (x * 7 + y * 3) % entries } else { (x * 3 + y * 5) & (entries - 1) };
+				let expected = [((i * 53) & 0xFF) as u16 * 257, ((i * 97 + 11) & 0xFF) as u16 * 257, (255usize.wrapping_sub(i * 29) & 0xFF) as u16 * 257];
+				let a = if i < entries / 2 { ((i * 50) & 0xFF) as u16 * 257 } else { 0xFFFF };
+				assert_eq!(&rgb[3 * j..3 * j + 3], &expected, "{} ({}, {})", path, x, y);
+				assert_eq!(alpha[j], a, "{} ({}, {})", path, x, y);
+			}
+		}
+	}
+}
+
+#[test]
+fn test_decode_interlaced() {
+	for &name in &["rgba_8bit", "rgb_16bit", "gray_2bit", "palette_4bit"] {
+		let interlaced = png16::decode_16bit_png(&format!("./test_images/{}_interlaced.png", name)).unwrap();
+		let png = png16::decode_16bit_png(&format!("./test_images/{}.png", name)).unwrap();
+		assert_eq!(interlaced.ihdr.interlace, 1);
+		assert_eq!(interlaced.image, png.image, "{}", name);
+	}
+
+	// 3x3 Leaves Passes 2 And 3 Empty
+	let png = png16::decode_16bit_png("./test_images/gray_8bit_interlaced_3x3.png").unwrap();
+	let rgb = png.rgb();
+	for y in 0..3 {
+		for x in 0..3 {
+			assert_eq!(rgb[3 * (x + y * 3)], ((x * 3 + y * 5) * 257) as u16);
+		}
+	}
+}
+
+#[test]
+fn test_encode_interlaced() {
+	let result = std::env::temp_dir().join("png16_encode_interlaced.png");
+	let result = result.to_str().unwrap();
+
+	for &(source, interlace) in &[("./test_images/rgba_8bit.png", 1), ("./test_images/gray_8bit_interlaced_3x3.png", 1), ("./test_images/rgb_16bit_interlaced.png", 0)] {
+		let mut png = png16::decode_16bit_png(source).unwrap();
+		let (rgb, alpha) = (png.rgb(), png.alpha());
+		png.ihdr.interlace = interlace;
+		png16::encode_png(png, png16::DEPTH_16, result).unwrap();
+
+		let png = png16::decode_16bit_png(result).unwrap();
+		assert_eq!(png.ihdr.interlace, interlace);
+		assert_eq!(png.rgb(), rgb, "{}", source);
+		assert_eq!(png.alpha(), alpha, "{}", source);
+	}
+}
+
+#[test]
+fn test_decode_from_reader() {
+	let mut bytes = vec![];
+	std::fs::File::open("./test_images/palette_4bit_interlaced.png").unwrap().read_to_end(&mut bytes).unwrap();
+	let png = png16::decode_16bit_png("./test_images/palette_4bit_interlaced.png").unwrap();
+
+	let from_bytes = png16::decode_16bit_png_from_bytes(&bytes).unwrap();
+	assert_eq!(from_bytes.image, png.image);
+	assert_eq!(from_bytes.header, png.header);
+
+	// Chain Is Read Only, No Seek
+	let (head, tail) = bytes.split_at(21);
+	let from_reader = png16::decode_16bit_png_from_reader(head.chain(tail)).unwrap();
+	assert_eq!(from_reader.image, png.image);
+}
+
+#[test]
+fn test_encode_to_writer() {
+	let png = png16::decode_16bit_png("./test_images/rgba_8bit.png").unwrap();
+
+	let mut out = Vec::<u8>::new();
+	png16::encode_png_to_writer(&png, png16::DEPTH_8, &mut out).unwrap();
+
+	let result = std::env::temp_dir().join("png16_encode_to_writer.png");
+	let result = result.to_str().unwrap();
+	png16::encode_png(png, png16::DEPTH_8, result).unwrap();
+
+	let mut bytes = vec![];
+	std::fs::File::open(result).unwrap().read_to_end(&mut bytes).unwrap();
+	assert_eq!(out, bytes);
+
+	let decoded = png16::decode_16bit_png_from_bytes(&out).unwrap();
+	assert_eq!(decoded.ihdr.depth, png16::DEPTH_8);
+}
+
+#[test]
+fn test_crc_check() {
+	let mut bytes = vec![];
+	std::fs::File::open("./test_images/palette_2bit.png").unwrap().read_to_end(&mut bytes).unwrap();
+
+	// IHDR CRC At 29, PLTE CRC Follows Its 12 Byte Palette, Last Byte Before IEND Is The IDAT CRC
+	let plte_crc = 33 + 8 + 12;
+	let idat_crc = bytes.len() - 13;
+	for &(offset, chunk) in &[(29, png16::ffi::IHDR), (plte_crc, png16::ffi::PLTE), (idat_crc, png16::ffi::IDAT)] {
+		let mut corrupt = bytes.clone();
+		corrupt[offset] ^= 0xFF;
+
+		let strict = png16::DecodeOptions { crc_check: png16::CrcCheck::Strict, ..Default::default() };
+		match png16::decode_16bit_png_with_options(&corrupt[..], &strict) {
+			Err(png16::Error::CrcMismatch { chunk: c, expected, found, .. }) => {
+				assert_eq!(c, chunk);
+				assert!(expected != found);
+			},
+			other => panic!("Expected CRC Mismatch: {:?}", other.err()),
+		}
+
+		let warn = png16::DecodeOptions { crc_check: png16::CrcCheck::Warn, ..Default::default() };
+		let png = png16::decode_16bit_png_with_options(&corrupt[..], &warn).unwrap();
+		assert_eq!(png.warnings.len(), 1);
+
+		let ignore = png16::DecodeOptions { crc_check: png16::CrcCheck::Ignore, ..Default::default() };
+		let png = png16::decode_16bit_png_with_options(&corrupt[..], &ignore).unwrap();
+		assert!(png.warnings.is_empty());
+	}
+	assert!(png16::decode_16bit_png_from_bytes(&bytes).unwrap().warnings.is_empty());
+}
+
+#[test]
+fn test_errors() {
+	let mut bytes = vec![];
+	std::fs::File::open("./test_images/gray_8bit.png").unwrap().read_to_end(&mut bytes).unwrap();
+
+	let mut corrupt = bytes.clone();
+	corrupt[0] = 0;
+	match png16::decode_16bit_png_from_bytes(&corrupt) {
+		Err(png16::Error::InvalidSignature { offset }) => assert_eq!(offset, 0),
+		other => panic!("Expected Invalid Signature: {:?}", other.err()),
+	}
+
+	// Zero Width
+	let mut corrupt = bytes.clone();
+	for b in &mut corrupt[16..20] {
+		*b = 0;
+	}
+	let ignore = png16::DecodeOptions { crc_check: png16::CrcCheck::Ignore, ..Default::default() };
+	match png16::decode_16bit_png_with_options(&corrupt[..], &ignore) {
+		Err(png16::Error::InvalidHeader { chunk, offset, field }) => {
+			assert_eq!(chunk, png16::ffi::IHDR);
+			assert_eq!(offset, 16);
+			assert_eq!(field, "Width");
+		},
+		other => panic!("Expected Invalid Header: {:?}", other.err()),
+	}
+
+	match png16::decode_16bit_png_from_bytes(&bytes[..bytes.len() - 20]) {
+		Err(e @ png16::Error::Truncated { .. }) => {
+			assert_eq!(e.chunk(), png16::ffi::IDAT);
+			assert_eq!(e.offset(), 33);
+		},
+		other => panic!("Expected Truncated: {:?}", other.err()),
+	}
+
+	match png16::decode_16bit_png("./test_images/missing.png") {
+		Err(e @ png16::Error::Io { .. }) => assert_eq!(std::io::Error::from(e).kind(), std::io::ErrorKind::NotFound),
+		other => panic!("Expected I/O Error: {:?}", other.err()),
+	}
+
+	let png = png16::decode_16bit_png_from_bytes(&bytes).unwrap();
+	match png16::encode_png_to_writer(&png, 4, vec![]) {
+		Err(png16::Error::Unsupported { feature, .. }) => assert_eq!(feature, "Bit Depth"),
+		other => panic!("Expected Unsupported: {:?}", other.err()),
+	}
+}
+
+#[test]
+fn test_decode_limits() {
+	// 70000 Pixels Wide, Past The Old 65535 Cap
+	let png = match png16::decode_16bit_png("./test_images/gray_1bit_wide.png") {
+		Ok(png) => png,
+		Err(e) => panic!("Error Decoding PNG: {:?}", e),
+	};
+	assert_eq!(png.ihdr.width, 70000);
+	let rgb = png.rgb();
+	assert_eq!(rgb.len(), 70000 * 3 * 3);
+	for y in 0..3 {
+		for x in [0, 4, 5, 69999].iter() {
+			let v = if (x * 7 + y * 3) / 5 % 2 == 1 { 0xFFFF } else { 0 };
+			assert_eq!(rgb[3 * (x + 70000 * y)], v);
+		}
+	}
+
+	let limited = |limits: png16::DecodeLimits| {
+		let options = png16::DecodeOptions { limits, ..Default::default() };
+		let img = std::fs::File::open("./test_images/rgb_8bit.png").unwrap();
+		match png16::decode_16bit_png_with_options(img, &options) {
+			Err(png16::Error::LimitExceeded { limit, .. }) => limit,
+			other => panic!("Expected Limit Exceeded: {:?}", other.err()),
+		}
+	};
+	assert_eq!(limited(png16::DecodeLimits { max_width: 8, ..Default::default() }), "Width");
+	assert_eq!(limited(png16::DecodeLimits { max_height: 6, ..Default::default() }), "Height");
+	assert_eq!(limited(png16::DecodeLimits { max_pixels: 62, ..Default::default() }), "Pixels");
+	assert_eq!(limited(png16::DecodeLimits { max_bytes: 1024, ..Default::default() }), "Bytes Allocated");
+
+	// Compressed IDAT Bytes Count Against max_bytes Together With The Decode Buffers:
+	// The Same Image Stored And Deflated Needs Exactly Its Extra IDAT Bytes More
+	let png = png16::decode_16bit_png("./test_images/rgb_8bit.png").unwrap();
+	let get_idat_bytes = |bytes: &[u8]| {
+		let mut idat_bytes = 0;
+		let mut offset = 8;
+		while offset + 8 <= bytes.len() {
+			let length = ((bytes[offset] as usize) << 24) | ((bytes[offset + 1] as usize) << 16) | ((bytes[offset + 2] as usize) << 8) | bytes[offset + 3] as usize;
+			if &bytes[offset + 4..offset + 8] == b"IDAT" {
+				idat_bytes += length as u64;
+			}
+			offset += 12 + length;
+		}
+		idat_bytes
+	};
+	// Smallest max_bytes That Decodes, One Less Fails Reading IDAT
+	let get_min_bytes = |bytes: &[u8]| {
+		let decode = |max_bytes: u64| {
+			let options = png16::DecodeOptions { limits: png16::DecodeLimits { max_bytes, ..Default::default() }, ..Default::default() };
+			png16::decode_16bit_png_with_options(bytes, &options)
+		};
+		let (mut low, mut high) = (0, png16::DecodeLimits::default().max_bytes);
+		while high - low > 1 {
+			let mid = low + (high - low) / 2;
+			if decode(mid).is_ok() { high = mid } else { low = mid }
+		}
+		match decode(high - 1) {
+			Err(png16::Error::LimitExceeded { chunk, limit, .. }) => assert_eq!((chunk, limit), (png16::ffi::IDAT, "Bytes Allocated")),
+			other => panic!("Expected Limit Exceeded: {:?}", other.err()),
+		}
+		high
+	};
+	let encoded = |options: &png16::EncodeOptions| {
+		let mut out = vec![];
+		png16::encode_png_with_options(&png, png16::DEPTH_8, &mut out, options).unwrap();
+		out
+	};
+	let (stored, deflated) = (encoded(&png16::EncodeOptions::store()), encoded(&png16::EncodeOptions { level: 9, ..Default::default() }));
+	assert!(get_idat_bytes(&stored) > get_idat_bytes(&deflated));
+	assert_eq!(get_min_bytes(&stored) - get_min_bytes(&deflated), get_idat_bytes(&stored) - get_idat_bytes(&deflated));
+
+	// input_1.png Carries An ICC Profile Before IDAT
+	let options = png16::DecodeOptions { limits: png16::DecodeLimits { max_metadata_bytes: 64, ..Default::default() }, ..Default::default() };
+	let img = std::fs::File::open("./test_images/input_1.png").unwrap();
+	match png16::decode_16bit_png_with_options(img, &options) {
+		Err(png16::Error::LimitExceeded { limit, .. }) => assert_eq!(limit, "Metadata Bytes"),
+		other => panic!("Expected Limit Exceeded: {:?}", other.err()),
+	}
+
+	// Many Small zTXt Chunks Share One Inflate Budget, Before And After IDAT
+	let mut png = png16::PNG::from_gray16(1, 1, vec![0]).unwrap();
+	for i in 0..100 {
+		let mut chunk = png16::TextChunk::compressed(&format!("Bomb {}", i), &"a".repeat(1000));
+		chunk.after_idat = i % 2 == 1;
+		png.metadata.add_text(chunk).unwrap();
+	}
+	let mut bytes = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_8, &mut bytes).unwrap();
+	assert!(bytes.len() < 8192);
+	let bomb = |max_metadata_bytes: u64| png16::DecodeOptions { limits: png16::DecodeLimits { max_metadata_bytes, ..Default::default() }, ..Default::default() };
+	for &(max_metadata_bytes, ok) in [(65536, false), (100000, true)].iter() {
+		match png16::decode_16bit_png_with_options(&bytes[..], &bomb(max_metadata_bytes)) {
+			Ok(decoded) => assert!(ok && decoded.metadata.text.len() == 100),
+			Err(png16::Error::LimitExceeded { chunk, limit, .. }) => assert!(!ok && chunk == png16::ffi::zTXt && limit == "Metadata Bytes"),
+			Err(e) => panic!("Unexpected Error: {:?}", e),
+		}
+		let decoded = png16::RowDecoder::with_options(&bytes[..], &bomb(max_metadata_bytes)).and_then(|mut decoder| {
+			while decoder.next_row()?.is_some() {}
+			decoder.finish()
+		});
+		assert_eq!(decoded.is_ok(), ok);
+	}
+}
+
+fn get_chunk_types(bytes: &[u8]) -> Vec<String> {
+	let mut types = vec![];
+	let mut offset = 8;
+	while offset + 8 <= bytes.len() {
+		let length = ((bytes[offset] as usize) << 24) | ((bytes[offset + 1] as usize) << 16) | ((bytes[offset + 2] as usize) << 8) | bytes[offset + 3] as usize;
+		types.push(String::from_utf8_lossy(&bytes[offset + 4..offset + 8]).into_owned());
+		offset += 12 + length;
+	}
+	types
+}
+
+#[test]
+fn test_metadata() {
+	let png = match png16::decode_16bit_png("./test_images/rgb_16bit_metadata.png") {
+		Ok(png) => png,
+		Err(e) => panic!("Error Decoding PNG: {:?}", e),
+	};
+	let metadata = &png.metadata;
+	assert_eq!(metadata.gamma, Some(0.45455));
+	let chrm = metadata.chromaticities.unwrap();
+	assert_eq!(chrm.white, (0.3127, 0.329));
+	assert_eq!(chrm.blue, (0.15, 0.06));
+	assert_eq!(metadata.srgb, Some(png16::RenderingIntent::RelativeColorimetric));
+	let icc = metadata.icc_profile.as_ref().unwrap();
+	assert_eq!(icc.name, "Test Profile");
+	assert_eq!(icc.profile.len(), 1024);
+	assert_eq!(icc.profile[255], 255);
+	let dpi = metadata.physical.unwrap().dpi().unwrap();
+	assert!((dpi.0 - 300.0).abs() < 0.05 && (dpi.1 - 150.0).abs() < 0.05);
+	assert_eq!(metadata.time, Some(png16::Timestamp { year: 2024, month: 2, day: 29, hour: 13, minute: 45, second: 30 }));
+	assert_eq!(metadata.background, Some([0x1234, 0x5678, 0x9ABC]));
+	assert_eq!(metadata.significant_bits, Some([12, 14, 16, 16]));
+
+	// Only The Unknown Chunk Is Left Opaque
+	assert_eq!(get_chunk_types(&[&[0; 8][..], &png.header].concat()), vec!["prVt"]);
+
+	let mut out = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_16, &mut out).unwrap();
+	assert_eq!(get_chunk_types(&out), vec!["IHDR", "cHRM", "gAMA", "iCCP", "sBIT", "sRGB", "prVt", "bKGD", "pHYs", "tIME", "IDAT", "IEND"]);
+	let decoded = png16::decode_16bit_png_from_bytes(&out).unwrap();
+	assert_eq!(decoded.metadata, png.metadata);
+	assert_eq!(decoded.header, png.header);
+
+	// Palette Background Indices Resolve To The PLTE Entry
+	let png = png16::decode_16bit_png("./test_images/palette_4bit_metadata.png").unwrap();
+	assert_eq!(png.metadata.background, Some([0, 128 * 257, 0xFFFF]));
+	assert_eq!(png.metadata.significant_bits, Some([5, 6, 5, 8]));
+
+	let mut out = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_8, &mut out).unwrap();
+	let decoded = png16::decode_16bit_png_from_bytes(&out).unwrap();
+	assert_eq!(decoded.ihdr.color_type, png16::COLOR_TYPE_RGB);
+	assert_eq!(decoded.metadata, png.metadata);
+}
+
+#[test]
+fn test_text_chunks() {
+	let mut png = match png16::decode_16bit_png("./test_images/rgb_8bit_text.png") {
+		Ok(png) => png,
+		Err(e) => panic!("Error Decoding PNG: {:?}", e),
+	};
+	let keywords: Vec<&str> = png.metadata.text.iter().map(|chunk| chunk.keyword.as_str()).collect();
+	assert_eq!(keywords, vec!["Title", "Render Settings", "Source", "Comment", "Title"]);
+	assert_eq!(png.metadata.get_text("Title"), Some("Caf\u{e9} Render"));
+	assert_eq!(png.metadata.get_text("Render Settings").unwrap().len(), 12 * 20);
+	assert_eq!(png.metadata.get_text("Source"), Some("アセット 42"));
+	assert_eq!(png.metadata.get_text("Comment"), Some("Rendered ✓"));
+	match png.metadata.text[2].kind {
+		png16::TextKind::International { compressed, ref language, ref translated_keyword } => {
+			assert!(!compressed);
+			assert_eq!(language, "ja");
+			assert_eq!(translated_keyword, "ソース");
+		},
+		ref kind => panic!("Expected iTXt: {:?}", kind),
+	}
+	assert_eq!(png.metadata.text[1].kind, png16::TextKind::Compressed);
+	assert!(png.header.is_empty());
+
+	// Replacing Collapses Duplicates, Non Latin-1 Text Moves To iTXt
+	png.metadata.set_text("Title", "タイトル").unwrap();
+	assert_eq!(png.metadata.text.iter().filter(|chunk| chunk.keyword == "Title").count(), 1);
+	assert_eq!(png.metadata.text[0].text, "タイトル");
+	assert!(png.metadata.remove_text("Comment"));
+	assert!(!png.metadata.remove_text("Comment"));
+	png.metadata.add_text(png16::TextChunk::compressed("Asset ID", "a1b2c3")).unwrap();
+	png.metadata.set_text("Author", "Render Farm").unwrap();
+	assert!(png.metadata.add_text(png16::TextChunk::new(" Bad", "x")).is_err());
+	// Chunks Built By Hand Move To iTXt Too Rather Than Lose Characters
+	png.metadata.add_text(png16::TextChunk { keyword: "Note".to_string(), text: "Ω".to_string(), kind: png16::TextKind::Text, after_idat: false }).unwrap();
+	png.metadata.add_text(png16::TextChunk::compressed("Notes", "ΩΩ")).unwrap();
+	assert_eq!(png.metadata.text[png.metadata.text.len() - 1].kind, png16::TextKind::International { compressed: true, language: String::new(), translated_keyword: String::new() });
+	png.metadata.add_text(png16::TextChunk::compressed("Latin", "Caf\u{e9}")).unwrap();
+	assert_eq!(png.metadata.text[png.metadata.text.len() - 1].kind, png16::TextKind::Compressed);
+	assert!(png.metadata.set_text("", "x").is_err());
+
+	let mut out = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_8, &mut out).unwrap();
+	assert_eq!(get_chunk_types(&out), vec!["IHDR", "iTXt", "zTXt", "iTXt", "zTXt", "tEXt", "iTXt", "iTXt", "zTXt", "IDAT", "IEND"]);
+	let decoded = png16::decode_16bit_png_from_bytes(&out).unwrap();
+	assert_eq!(decoded.metadata.text, png.metadata.text);
+	assert_eq!(decoded.metadata.get_text("Author"), Some("Render Farm"));
+	assert_eq!(decoded.metadata.get_text("Notes"), Some("ΩΩ"));
+}
+
+#[test]
+fn test_trailer_chunks() {
+	let png = match png16::decode_16bit_png("./test_images/gray_8bit_trailer.png") {
+		Ok(png) => png,
+		Err(e) => panic!("Error Decoding PNG: {:?}", e),
+	};
+	assert!(png.metadata.time_after_idat);
+	assert_eq!(png.metadata.time.unwrap().year, 2023);
+	assert!(!png.metadata.text[0].after_idat);
+	assert!(png.metadata.text[1].after_idat);
+	assert_eq!(png.metadata.get_text("Comment"), Some("after the pixels"));
+	// eXIf Has No Typed Representation And Stays Opaque
+	assert_eq!(get_chunk_types(&[&[0; 8][..], &png.trailer].concat()), vec!["eXIf"]);
+
+	// Written Back In The Order Of The File
+	let bytes = std::fs::read("./test_images/gray_8bit_trailer.png").unwrap();
+	assert_eq!(get_chunk_types(&bytes), vec!["IHDR", "tEXt", "IDAT", "tIME", "eXIf", "tEXt", "IEND"]);
+	assert_eq!(png.metadata.after_idat_order, vec![png16::ffi::tIME, 0x65584966, png16::ffi::tEXt]);
+	let mut out = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_8, &mut out).unwrap();
+	assert_eq!(get_chunk_types(&out), get_chunk_types(&bytes));
+	let decoded = png16::decode_16bit_png_from_bytes(&out).unwrap();
+	assert_eq!(decoded.metadata, png.metadata);
+	assert_eq!(decoded.trailer, png.trailer);
+
+	// Stripped Chunks Leave No Gap, New Text After IDAT Follows The Recorded Chunks
+	let mut png = decoded;
+	png16::strip_metadata(&mut png, &[png16::ffi::tIME, png16::ffi::tEXt], &png16::EncodeOptions::default()).unwrap();
+	let mut chunk = png16::TextChunk::new("Late", "added");
+	chunk.after_idat = true;
+	png.metadata.add_text(chunk).unwrap();
+	let mut out = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_8, &mut out).unwrap();
+	assert_eq!(get_chunk_types(&out), vec!["IHDR", "tEXt", "IDAT", "tIME", "tEXt", "tEXt", "IEND"]);
+	assert_eq!(png16::decode_16bit_png_from_bytes(&out).unwrap().metadata.get_text("Late"), Some("added"));
+
+	// ImageMagick Writes Its EXIF Text After IDAT
+	let png = png16::decode_16bit_png("./test_images/input_1.png").unwrap();
+	assert_eq!(png.metadata.get_text("exif:Make"), Some("SONY"));
+	assert!(png.metadata.text.iter().filter(|chunk| chunk.after_idat).count() > 40);
+
+	// Missing IEND, IDAT After Another Chunk
+	let mut bytes = vec![];
+	std::fs::File::open("./test_images/gray_8bit_trailer.png").unwrap().read_to_end(&mut bytes).unwrap();
+	match png16::decode_16bit_png_from_bytes(&bytes[..bytes.len() - 12]) {
+		Err(png16::Error::Truncated { .. }) => (),
+		other => panic!("Expected Truncated: {:?}", other.err()),
+	}
+	let iend = bytes.len() - 12;
+	let mut split = bytes[..iend].to_vec();
+	split.extend_from_slice(&[0, 0, 0, 0, b'I', b'D', b'A', b'T', 0x35, 0xAF, 0x06, 0x1E]);
+	split.extend_from_slice(&bytes[iend..]);
+	match png16::decode_16bit_png_from_bytes(&split) {
+		Err(png16::Error::InvalidChunk { chunk, .. }) => assert_eq!(chunk, png16::ffi::IDAT),
+		other => panic!("Expected Invalid Chunk: {:?}", other.err()),
+	}
+}
+
+#[test]
+fn test_chunk_policy() {
+	let png = match png16::decode_16bit_png("./test_images/rgb_8bit_chunks.png") {
+		Ok(png) => png,
+		Err(e) => panic!("Error Decoding PNG: {:?}", e),
+	};
+	assert!(png16::is_safe_to_copy(png16::ffi::tEXt) && !png16::is_safe_to_copy(png16::ffi::tRNS));
+	assert!(png16::is_critical(png16::ffi::PLTE) && !png16::is_critical(png16::ffi::tRNS));
+
+	let encoded = |policy: png16::ChunkPolicy, depth: u8| {
+		let mut out = vec![];
+		png16::encode_png_with_options(&png, depth, &mut out, &png16::EncodeOptions { chunks: policy, ..Default::default() }).unwrap();
+		get_chunk_types(&out)
+	};
+	assert_eq!(encoded(png16::ChunkPolicy::KeepAll, png16::DEPTH_8), vec!["IHDR", "tRNS", "prVt", "prVT", "IDAT", "laTE", "laTe", "IEND"]);
+	assert_eq!(encoded(png16::ChunkPolicy::SafeToCopy, png16::DEPTH_8), vec!["IHDR", "tRNS", "prVt", "IDAT", "laTe", "IEND"]);
+	assert_eq!(encoded(png16::ChunkPolicy::Keep(vec![0x70725654]), png16::DEPTH_8), vec!["IHDR", "prVT", "IDAT", "IEND"]);
+	assert_eq!(encoded(png16::ChunkPolicy::StripAll, png16::DEPTH_8), vec!["IHDR", "IDAT", "IEND"]);
+	// tRNS Is Tied To The Bit Depth
+	assert_eq!(encoded(png16::ChunkPolicy::KeepAll, png16::DEPTH_16), vec!["IHDR", "prVt", "prVT", "IDAT", "laTE", "laTe", "IEND"]);
+
+	let mut out = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_8, &mut out).unwrap();
+	assert_eq!(get_chunk_types(&out), encoded(png16::ChunkPolicy::SafeToCopy, png16::DEPTH_8));
+}
+
+#[test]
+fn test_strip_metadata() {
+	let mut png = match png16::decode_16bit_png("./test_images/rgb_16bit_metadata.png") {
+		Ok(png) => png,
+		Err(e) => panic!("Error Decoding PNG: {:?}", e),
+	};
+	png.metadata.set_text("Comment", "Dropped").unwrap();
+
+	let mut full = vec![];
+	png16::encode_png_with_options(&png, png16::DEPTH_16, &mut full, &png16::EncodeOptions { chunks: png16::ChunkPolicy::KeepAll, ..Default::default() }).unwrap();
+	let mut optimized = vec![];
+	png16::encode_png_with_options(&png, png16::DEPTH_16, &mut optimized, &png16::EncodeOptions::optimize()).unwrap();
+	assert_eq!(get_chunk_types(&optimized), vec!["IHDR", "cHRM", "gAMA", "iCCP", "sRGB", "IDAT", "IEND"]);
+
+	// Keep Only sRGB
+	let mut srgb = vec![];
+	let options = png16::EncodeOptions { strip: Some(vec![png16::ffi::sRGB]), ..Default::default() };
+	png16::encode_png_with_options(&png, png16::DEPTH_16, &mut srgb, &options).unwrap();
+	assert_eq!(get_chunk_types(&srgb), vec!["IHDR", "sRGB", "IDAT", "IEND"]);
+
+	// The Report Matches The Difference In File Size
+	let keep_all = png16::EncodeOptions { chunks: png16::ChunkPolicy::KeepAll, ..Default::default() };
+	let saved = png16::strip_metadata(&mut png, &png16::RENDERING_CHUNKS, &keep_all).unwrap();
+	assert_eq!(saved, full.len() - optimized.len());
+	assert!(png.header.is_empty() && png.metadata.text.is_empty() && png.metadata.time.is_none());
+	assert!(png.metadata.icc_profile.is_some() && png.metadata.gamma.is_some());
+	assert_eq!(png16::strip_metadata(&mut png, &png16::RENDERING_CHUNKS, &keep_all).unwrap(), 0);
+
+	let mut out = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_16, &mut out).unwrap();
+	assert_eq!(out, optimized);
+
+	// Measured Against The Caller's Options, Unsafe-To-Copy Chunks Never Count Under SafeToCopy
+	let encoded = |png: &png16::PNG| {
+		let mut out = vec![];
+		png16::encode_png_to_writer(png, png16::DEPTH_8, &mut out).unwrap();
+		out
+	};
+	let mut png = png16::decode_16bit_png("./test_images/rgb_8bit_chunks.png").unwrap();
+	let full = encoded(&png);
+	let saved = png16::strip_metadata(&mut png, &png16::RENDERING_CHUNKS, &png16::EncodeOptions::default()).unwrap();
+	assert_eq!(saved, full.len() - encoded(&png).len());
+
+	// Palette Chunks Survive Stripping, tRNS Still Selects RGBA Row Encoding
+	let mut png = png16::decode_16bit_png("./test_images/palette_4bit.png").unwrap();
+	let header = png.header.clone();
+	let full = encoded(&png);
+	assert_eq!(png16::strip_metadata(&mut png, &png16::RENDERING_CHUNKS, &png16::EncodeOptions::default()).unwrap(), 0);
+	assert_eq!(png.header, header);
+	assert_eq!(encoded(&png), full);
+	let encoder = png16::RowEncoder::new(vec![], &png, png16::DEPTH_8, &png16::EncodeOptions::default()).unwrap();
+	assert_eq!(encoder.color_type(), png16::COLOR_TYPE_RGBA);
+}
+
+#[test]
+fn test_filters() {
+	use png16::filter::{self, FilterType};
+
+	for &bpp in &[1, 2, 3, 4, 6, 8] {
+		let row_bytes = bpp * 7;
+		let rows: Vec<Vec<u8>> = (0..4).map(|y| (0..row_bytes).map(|i| ((i * 97 + y * 61) ^ (i * i)) as u8).collect()).collect();
+		for &filter_type in filter::FILTER_TYPES.iter() {
+			let mut prev: &[u8] = &[];
+			for row in &rows {
+				let mut filterd = vec![];
+				filter::filter_row(filter_type, bpp, prev, row, &mut filterd);
+				assert_eq!(FilterType::from_u8(filterd[0]), Some(filter_type));
+
+				let mut recon = filterd[1..].to_vec();
+				filter::unfilter_row(filter_type, bpp, prev, &mut recon);
+				assert_eq!(&recon, row, "{:?} With {} Bytes Per Pixel", filter_type, bpp);
+				prev = row;
+			}
+		}
+	}
+	assert_eq!(FilterType::from_u8(5), None);
+
+	// Avg Is Orig(x) - floor((a + b) / 2): 200 - (250 + 100) / 2 Wraps To 25
+	let mut filterd = vec![];
+	filter::filter_row(FilterType::Avg, 1, &[0, 100], &[250, 200], &mut filterd);
+	assert_eq!(filterd, vec![3, 250, 25]);
+	// Paeth: a = 10, b = 20, c = 5 Predicts b
+	let mut filterd = vec![];
+	filter::filter_row(FilterType::Paeth, 1, &[5, 20], &[10, 30], &mut filterd);
+	assert_eq!(filterd, vec![4, 5, 10]);
+}
+
+// ********************************************************
+// Every Row Uses One Filter, So Row Zero Predicts From A Scanline Of Zeros
+// Interlaced: Every Adam7 Pass Starts With A Paeth Row
+// ********************************************************
+#[test]
+fn test_first_row_filters() {
+	let inputs = [
+		("first_row_sub_rgba_16bit.png", 4, 16),
+		("first_row_up_gray_8bit.png", 1, 8),
+		("first_row_avg_rgb_8bit.png", 3, 8),
+		("first_row_paeth_gray_alpha_16bit.png", 2, 16),
+		("first_row_paeth_rgba_8bit_interlaced.png", 4, 8),
+	];
+	for &(name, channels, depth) in &inputs {
+		let png = png16::decode_16bit_png(&format!("test_images/{}", name)).unwrap();
+		assert_eq!((png.ihdr.width, png.ihdr.height), (9, 5));
+		let sample = |x: usize, y: usize, c: usize| {
+			let v = x * 37 + y * 101 + c * 53;
+			if depth == 16 { (v * 257) as u16 } else { (v & 0xFF) as u16 * 257 }
+		};
+		let (rgb, alpha) = (png.rgb(), png.alpha());
+		for y in 0..5 {
+			for x in 0..9 {
+				let i = y * 9 + x;
+				let expected: Vec<u16> = (0..3).map(|c| sample(x, y, if channels < 3 { 0 } else { c })).collect();
+				let a = if channels == 2 || channels == 4 { sample(x, y, channels - 1) } else { 0xFFFF };
+				assert_eq!(&rgb[i * 3..i * 3 + 3], &expected[..], "{} At ({}, {})", name, x, y);
+				assert_eq!(alpha[i], a, "{} At ({}, {})", name, x, y);
+			}
+		}
+	}
+}
+
+#[test]
+fn test_filter_strategies() {
+	use png16::filter::{FilterStrategy, FilterType};
+
+	let png = png16::decode_16bit_png("test_images/input_2.png").unwrap();
+	let strategies = [
+		FilterStrategy::Fixed(FilterType::None),
+		FilterStrategy::Fixed(FilterType::Paeth),
+		FilterStrategy::MinSum,
+		FilterStrategy::Entropy,
+		FilterStrategy::BruteForce,
+	];
+	let mut sizes = vec![];
+	for &strategy in &strategies {
+		let mut out = vec![];
+		let options = png16::EncodeOptions { filter: strategy, ..Default::default() };
+		png16::encode_png_with_options(&png, png.ihdr.depth, &mut out, &options).unwrap();
+		let decoded = png16::decode_16bit_png_from_bytes(&out).unwrap();
+		assert_eq!((decoded.rgb(), decoded.alpha()), (png.rgb(), png.alpha()), "{:?}", strategy);
+		sizes.push(out.len());
+	}
+	assert_eq!(png16::EncodeOptions::default().filter, FilterStrategy::MinSum);
+	// The Heuristics Stay Close To Brute Force And Beat No Filtering
+	assert!(sizes[2] * 100 <= sizes[4] * 105 && sizes[2] < sizes[0]);
+	assert!(sizes[3] * 100 <= sizes[4] * 105 && sizes[3] < sizes[0]);
+
+	// Adaptive Selection Reports The Filter It Wrote
+	let mut filterd = vec![];
+	let filter_type = png16::filter::filter_row_with_strategy(FilterStrategy::MinSum, 1, &[], &[7, 7, 7, 7], &mut filterd).unwrap();
+	assert_eq!((filter_type, filterd), (FilterType::Sub, vec![1, 7, 0, 0, 0]));
+}
+
+#[test]
+fn test_compression_options() {
+	let png = png16::decode_16bit_png("test_images/input_2.png").unwrap();
+	let encode = |options: &png16::EncodeOptions| {
+		let mut out = vec![];
+		png16::encode_png_with_options(&png, png16::DEPTH_16, &mut out, options).unwrap();
+		let decoded = png16::decode_16bit_png_from_bytes(&out).unwrap();
+		assert_eq!((decoded.rgb(), decoded.alpha()), (png.rgb(), png.alpha()), "{:?}", options);
+		out.len()
+	};
+
+	// Stored: 6 Bytes RGB16 (The Opaque Alpha Is Dropped) + 1 Filter Byte Per Row, Plus Deflate Block Headers
+	let raw = 115 * (172 * 6 + 1);
+	let stored = encode(&png16::EncodeOptions::store());
+	assert!(stored > raw);
+	let fast = encode(&png16::EncodeOptions { level: 1, ..Default::default() });
+	let best = encode(&png16::EncodeOptions { level: 9, ..Default::default() });
+	assert!(best <= fast && fast < stored);
+
+	for &zlib_strategy in &[png16::ZlibStrategy::Filtered, png16::ZlibStrategy::HuffmanOnly, png16::ZlibStrategy::Rle] {
+		assert!(encode(&png16::EncodeOptions { zlib_strategy, ..Default::default() }) < stored);
+	}
+
+	let mut out = vec![];
+	match png16::encode_png_with_options(&png, png16::DEPTH_16, &mut out, &png16::EncodeOptions { level: 10, ..Default::default() }) {
+		Err(png16::Error::Unsupported { feature: "Compression Level", .. }) => (),
+		other => panic!("{:?}", other),
+	}
+}
+
+#[test]
+fn test_row_decoder() {
+	for entry in std::fs::read_dir("test_images").unwrap() {
+		let path = entry.unwrap().path();
+		if path.extension().is_none_or(|extension| extension != "png") {
+			continue;
+		}
+		let png = png16::decode_16bit_png(path.to_str().unwrap()).unwrap();
+		let file = std::io::BufReader::new(std::fs::File::open(&path).unwrap());
+		if png.ihdr.interlace != 0 {
+			match png16::RowDecoder::new(file) {
+				Err(png16::Error::Unsupported { feature, .. }) => assert_eq!(feature, "Interlaced Row Decoding"),
+				other => panic!("Expected Unsupported: {:?}", other.err()),
+			}
+			continue;
+		}
+
+		let mut decoder = png16::RowDecoder::new(file).unwrap();
+		assert_eq!((decoder.ihdr.width, decoder.ihdr.height), (png.ihdr.width, png.ihdr.height));
+		let (mut rgb, mut alpha) = (vec![], vec![]);
+		let mut rows = 0;
+		while decoder.next_rgb_a(&mut rgb, &mut alpha).unwrap() {
+			rows += 1;
+			assert_eq!(alpha.len(), rows * png.ihdr.width as usize);
+		}
+		assert_eq!(rows, png.ihdr.height as usize);
+		assert!(rgb == png.rgb() && alpha == png.alpha(), "{:?}", path);
+
+		decoder.finish().unwrap();
+		assert_eq!((&decoder.header, &decoder.trailer, &decoder.metadata), (&png.header, &png.trailer, &png.metadata), "{:?}", path);
+	}
+
+	// Packed Rows Come Back As Stored, 1bit Gray Is 70000 Pixels Per 8750 Bytes
+	let mut decoder = png16::RowDecoder::new(std::fs::File::open("test_images/gray_1bit_wide.png").unwrap()).unwrap();
+	assert_eq!(decoder.next_row().unwrap().unwrap().len(), 8750);
+	// Rows Left Unread Are Skipped
+	decoder.finish().unwrap();
+
+	let mut bytes = vec![];
+	std::fs::File::open("test_images/input_2.png").unwrap().read_to_end(&mut bytes).unwrap();
+	let mut decoder = png16::RowDecoder::new(&bytes[..bytes.len() / 2]).unwrap();
+	loop {
+		match decoder.next_row() {
+			Ok(Some(_)) => (),
+			Err(e @ png16::Error::Truncated { .. }) => {
+				assert_eq!(e.chunk(), png16::ffi::IDAT);
+				break;
+			},
+			other => panic!("Expected Truncated: {:?}", other.err()),
+		}
+	}
+
+	// IDAT CRC Warnings Show Up Without Calling finish()
+	let mut offset = 8;
+	while &bytes[offset + 4..offset + 8] != b"IDAT" {
+		offset += 12 + ((bytes[offset] as usize) << 24 | (bytes[offset + 1] as usize) << 16 | (bytes[offset + 2] as usize) << 8 | bytes[offset + 3] as usize);
+	}
+	let length = (bytes[offset] as usize) << 24 | (bytes[offset + 1] as usize) << 16 | (bytes[offset + 2] as usize) << 8 | bytes[offset + 3] as usize;
+	assert!(&bytes[offset + 12 + length + 4..offset + 12 + length + 8] == b"IDAT");
+	let mut corrupt = bytes.clone();
+	corrupt[offset + 8 + length] ^= 0xFF;
+	let warn = png16::DecodeOptions { crc_check: png16::CrcCheck::Warn, ..Default::default() };
+	let mut decoder = png16::RowDecoder::with_options(&corrupt[..], &warn).unwrap();
+	while decoder.next_row().unwrap().is_some() {}
+	match decoder.warnings[..] {
+		[png16::Error::CrcMismatch { chunk, .. }] => assert_eq!(chunk, png16::ffi::IDAT),
+		ref warnings => panic!("Expected One CRC Mismatch: {:?}", warnings),
+	}
+
+	// PLTE Is Resolved Before The First Row, A Missing One Fails The Constructor
+	let bytes = std::fs::read("test_images/palette_4bit.png").unwrap();
+	assert_eq!(&bytes[37..41], b"PLTE");
+	let length = (bytes[33] as usize) << 24 | (bytes[34] as usize) << 16 | (bytes[35] as usize) << 8 | bytes[36] as usize;
+	let missing = [&bytes[..33], &bytes[33 + 12 + length..]].concat();
+	match png16::RowDecoder::new(&missing[..]).err() {
+		Some(png16::Error::InvalidChunk { chunk, reason, .. }) => assert_eq!((chunk, reason), (png16::ffi::PLTE, "Missing PLTE")),
+		other => panic!("Expected Missing PLTE: {:?}", other),
+	}
+}
+
+#[test]
+fn test_row_encoder() {
+	for name in &["input_2.png", "gray_8bit_trailer.png", "rgb_16bit_metadata.png", "palette_4bit.png"] {
+		let png = png16::decode_16bit_png(&format!("test_images/{}", name)).unwrap();
+		let width = png.ihdr.width as usize;
+		let options = png16::EncodeOptions { level: 9, ..Default::default() };
+
+		// Rows Come Straight From A RowDecoder, Nothing Holds The Whole Image
+		let file = std::fs::File::open(format!("test_images/{}", name)).unwrap();
+		let mut decoder = png16::RowDecoder::new(std::io::BufReader::new(file)).unwrap();
+		let mut encoder = png16::RowEncoder::new(vec![], &png, png16::DEPTH_16, &options).unwrap();
+		let (mut rgb, mut alpha) = (vec![], vec![]);
+		while decoder.next_rgb_a(&mut rgb, &mut alpha).unwrap() {
+			encoder.write_rgb_a(&rgb, &alpha).unwrap();
+			rgb.clear();
+			alpha.clear();
+		}
+		let out = encoder.finish().unwrap();
+
+		let decoded = png16::decode_16bit_png_from_bytes(&out).unwrap();
+		assert!(decoded.rgb() == png.rgb() && decoded.alpha() == png.alpha(), "{}", name);
+		if png.ihdr.color_type == png16::COLOR_TYPE_PALETTE {
+			// Translucent Palette Entries Need RGBA, PLTE And tRNS Are Dropped With The Palette
+			assert_eq!((decoded.ihdr.color_type, decoded.header.len()), (png16::COLOR_TYPE_RGBA, 0), "{}", name);
+			assert!(png.alpha().iter().any(|&a| a != 0xFFFF));
+		} else {
+			assert_eq!((&decoded.header, &decoded.trailer, &decoded.metadata), (&png.header, &png.trailer, &png.metadata), "{}", name);
+		}
+
+		// Every IDAT But The Last Is Full
+		let idat: Vec<usize> = (0..out.len() - 8).filter(|&i| &out[i + 4..i + 8] == b"IDAT").map(|i| {
+			((out[i] as usize) << 24) | ((out[i + 1] as usize) << 16) | ((out[i + 2] as usize) << 8) | out[i + 3] as usize
+		}).collect();
+		assert!(idat[..idat.len() - 1].iter().all(|&len| len == png16::ffi::MAX_IDAT_SIZE) && idat[idat.len() - 1] > 0, "{}", name);
+
+		// Same Bytes As The Whole Image Encoder When The Color Type Matches
+		let mut whole = vec![];
+		png16::encode_png_with_options(&png, png16::DEPTH_16, &mut whole, &options).unwrap();
+		if whole[25] == png.ihdr.color_type {
+			assert_eq!(out, whole, "{}", name);
+		}
+
+		let mut encoder = png16::RowEncoder::new(vec![], &png, png16::DEPTH_8, &options).unwrap();
+		match encoder.write_rgb_a(&png.rgb()[..3 * (width - 1)], &png.alpha()[..width - 1]) {
+			Err(png16::Error::InvalidImage { reason }) => assert_eq!(reason, "Row Length"),
+			other => panic!("Expected Invalid Image: {:?}", other.err()),
+		}
+		encoder.write_rgb_a(&png.rgb()[..3 * width], &png.alpha()[..width]).unwrap();
+		match encoder.finish() {
+			Err(png16::Error::InvalidImage { reason }) => assert_eq!(reason, "Fewer Rows Than Height"),
+			other => panic!("Expected Invalid Image: {:?}", other.err()),
+		}
+	}
+
+	let png = png16::decode_16bit_png("test_images/rgb_16bit_interlaced.png").unwrap();
+	match png16::RowEncoder::new(vec![], &png, png16::DEPTH_16, &Default::default()) {
+		Err(png16::Error::Unsupported { feature, .. }) => assert_eq!(feature, "Interlaced Row Encoding"),
+		other => panic!("Expected Unsupported: {:?}", other.err().map(|e| e.to_string())),
+	}
+
+	// Rows The Color Type Can't Hold Exactly Are Rejected, Not Converted
+	let png = png16::PNG::from_gray16(2, 1, vec![0, 0]).unwrap();
+	let mut encoder = png16::RowEncoder::new(vec![], &png, png16::DEPTH_16, &Default::default()).unwrap();
+	match encoder.write_rgb_a(&[0xFFFF, 0, 0, 0, 0, 0], &[0xFFFF, 0xFFFF]) {
+		Err(png16::Error::InvalidImage { reason }) => assert_eq!(reason, "Color Pixel In Gray Row"),
+		other => panic!("Expected Invalid Image: {:?}", other.err()),
+	}
+	match encoder.write_rgb_a(&[0xFFFF; 6], &[0, 0xFFFF]) {
+		Err(png16::Error::InvalidImage { reason }) => assert_eq!(reason, "Translucent Pixel Without Alpha"),
+		other => panic!("Expected Invalid Image: {:?}", other.err()),
+	}
+	match encoder.write_pixels(&[png16::Rgb16 { r: 300, g: 600, b: 900 }, png16::Rgb16 { r: 0, g: 0, b: 0 }]) {
+		Err(png16::Error::InvalidImage { reason }) => assert_eq!(reason, "Color Pixel In Gray Row"),
+		other => panic!("Expected Invalid Image: {:?}", other.err()),
+	}
+	encoder.write_pixels(&[png16::Rgb16 { r: 600, g: 600, b: 600 }, png16::Rgb16 { r: 0, g: 0, b: 0 }]).unwrap();
+	let decoded = png16::decode_16bit_png_from_bytes(&encoder.finish().unwrap()).unwrap();
+	assert_eq!((decoded.ihdr.color_type, decoded.image.samples), (png16::COLOR_TYPE_GRAY, vec![600, 0]));
+}
+
+#[test]
+fn test_image_buffer() {
+	use png16::{ChannelLayout, ImageBuffer};
+
+	// Samples Keep The Layout Of The Color Type, Palette With tRNS Is Rgba
+	for &(name, layout) in &[("gray_4bit", ChannelLayout::Gray), ("gray_alpha_16bit", ChannelLayout::GrayAlpha), ("rgb_8bit", ChannelLayout::Rgb), ("rgba_8bit", ChannelLayout::Rgba)] {
+		let png = png16::decode_16bit_png(&format!("./test_images/{}.png", name)).unwrap();
+		let image = &png.image;
+		assert_eq!(image.layout, layout, "{}", name);
+		assert_eq!(image.stride, image.width as usize * layout.channels());
+		assert_eq!(image.samples.len(), image.stride * image.height as usize);
+
+		// Planar Views Agree With The Interleaved Samples
+		let (rgb, alpha) = (image.rgb(), image.alpha());
+		assert_eq!(image.plane(0), rgb.iter().step_by(3).cloned().collect::<Vec<u16>>(), "{}", name);
+		if layout.has_alpha() {
+			assert_eq!(image.plane(layout.channels() - 1), alpha);
+		} else {
+			assert!(alpha.iter().all(|&a| a == 0xFFFF));
+		}
+		let (x, y) = (image.width - 1, image.height - 1);
+		let j = (x + y * image.width) as usize;
+		assert_eq!(image.get_rgba(x, y), [rgb[3 * j], rgb[3 * j + 1], rgb[3 * j + 2], alpha[j]]);
+	}
+
+	// Rows Padded Past width Encode Like Packed Rows
+	let mut png = png16::decode_16bit_png("./test_images/rgb_16bit.png").unwrap();
+	let mut packed = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_16, &mut packed).unwrap();
+	let mut padded = ImageBuffer { stride: png.image.stride + 5, samples: vec![0xABCD; (png.image.stride + 5) * png.image.height as usize], ..png.image.clone() };
+	for y in 0..png.image.height {
+		padded.row_mut(y).copy_from_slice(png.image.row(y));
+	}
+	png.image = padded;
+	let mut out = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_16, &mut out).unwrap();
+	assert_eq!(out, packed);
+
+	png.image.samples.truncate(10);
+	match png16::encode_png_to_writer(&png, png16::DEPTH_16, vec![]) {
+		Err(png16::Error::InvalidImage { reason }) => assert_eq!(reason, "Image Buffer Too Small"),
+		other => panic!("Expected Invalid Image: {:?}", other.err()),
+	}
+
+	assert!(ImageBuffer::from_samples(2, 2, ChannelLayout::GrayAlpha, vec![0; 7]).is_none());
+	let gray = ImageBuffer::from_samples(2, 1, ChannelLayout::GrayAlpha, vec![100, 200, 300, 400]).unwrap();
+	let rgba = gray.to_layout(ChannelLayout::Rgba);
+	assert_eq!(rgba.samples, vec![100, 100, 100, 200, 300, 300, 300, 400]);
+	assert_eq!(rgba.to_layout(ChannelLayout::Gray).samples, vec![100, 300]);
+	assert_eq!(ImageBuffer::new(3, 2, ChannelLayout::Rgb).samples.len(), 18);
+}
+
+#[test]
+#[should_panic(expected = "Pixel (2, 0) Out Of Bounds For 2x2 Image")]
+fn test_image_buffer_pixel_bounds() {
+	// Past The Row End Would Otherwise Read Pixel (0, 1)
+	let image = png16::ImageBuffer::from_samples(2, 2, png16::ChannelLayout::Gray, vec![1, 2, 3, 4]).unwrap();
+	image.pixel(2, 0);
+}
+
+#[test]
+#[should_panic(expected = "Channel 1 Out Of Bounds For 1 Channels")]
+fn test_image_buffer_plane_bounds() {
+	let image = png16::ImageBuffer::from_samples(2, 2, png16::ChannelLayout::Gray, vec![1, 2, 3, 4]).unwrap();
+	image.plane(1);
+}
+
+#[test]
+fn test_typed_pixels() {
+	use png16::{Luma16, Luma8, LumaA16, Rgb16, Rgb8, Rgba16, Rgba8};
+
+	let png = png16::decode_16bit_png("./test_images/rgba_8bit.png").unwrap();
+	let (width, height) = (png.ihdr.width, png.ihdr.height);
+	for y in 0..height {
+		for x in 0..width {
+			let (xs, ys) = (x as usize, y as usize);
+			let expected = Rgba8 {
+				r: ((xs * 31 + ys * 7) & 0xFF) as u8,
+				g: ((xs * 5 + ys * 13) & 0xFF) as u8,
+				b: ((xs * ys) & 0xFF) as u8,
+				a: ((255 - xs * 3 - ys) & 0xFF) as u8,
+			};
+			assert_eq!(png.get_pixel::<Rgba8>(x, y), expected);
+			let p: Rgba16 = png.get_pixel(x, y);
+			assert_eq!(p, Rgba16 { r: expected.r as u16 * 257, g: expected.g as u16 * 257, b: expected.b as u16 * 257, a: expected.a as u16 * 257 });
+			assert_eq!(png.get_pixel::<Rgb8>(x, y), Rgb8 { r: expected.r, g: expected.g, b: expected.b });
+		}
+	}
+
+	// pixels() Walks The Same Order As rows()
+	let pixels: Vec<Rgba16> = png.pixels().collect();
+	assert_eq!(pixels.len(), (width * height) as usize);
+	assert_eq!(png.rows::<Rgba16>().count(), height as usize);
+	assert!(png.rows::<Rgba16>().all(|row| row.count() == width as usize));
+	assert_eq!(png.rows().flatten().collect::<Vec<Rgba16>>(), pixels);
+	let rgb = png.rgb();
+	assert!(png.pixels::<Rgb16>().enumerate().all(|(j, p)| [p.r, p.g, p.b] == rgb[3 * j..3 * j + 3]));
+
+	// Gray Images Store Color As The Rounded Mean, Alpha They Lack Is Dropped
+	let mut png = png16::decode_16bit_png("./test_images/gray_8bit.png").unwrap();
+	png.put_pixel(1, 0, Rgba16 { r: 300, g: 600, b: 901, a: 0 });
+	assert_eq!(png.get_pixel::<LumaA16>(1, 0), LumaA16 { l: 600, a: 0xFFFF });
+	assert_eq!(png.get_pixel::<Rgb16>(1, 0), Rgb16 { r: 600, g: 600, b: 600 });
+	png.put_pixel(0, 0, Luma8 { l: 0x80 });
+	assert_eq!(png.get_pixel::<Luma16>(0, 0), Luma16 { l: 0x8080 });
+	// 16bit To 8bit Rounds
+	png.put_pixel(0, 0, Luma16 { l: 0x807F });
+	assert_eq!(png.get_pixel::<Luma8>(0, 0), Luma8 { l: 0x80 });
+
+	// RowEncoder Takes Typed Rows
+	let png = png16::decode_16bit_png("./test_images/rgb_16bit.png").unwrap();
+	let mut whole = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_16, &mut whole).unwrap();
+	let mut encoder = png16::RowEncoder::new(vec![], &png, png16::DEPTH_16, &Default::default()).unwrap();
+	for row in png.rows::<Rgb16>() {
+		encoder.write_pixels(&row.collect::<Vec<Rgb16>>()).unwrap();
+	}
+	assert_eq!(encoder.finish().unwrap(), whole);
+}
+
+#[test]
+#[should_panic(expected = "Pixel (0, 2) Out Of Bounds For 2x2 Image")]
+fn test_typed_pixel_bounds() {
+	let mut png = png16::PNG::from_gray16(2, 2, vec![1, 2, 3, 4]).unwrap();
+	assert_eq!(png.get_pixel::<png16::Luma16>(1, 1), png16::Luma16 { l: 4 });
+	png.put_pixel(0, 2, png16::Luma16 { l: 5 });
+}
+
+#[test]
+fn test_png_from_samples() {
+	let (width, height) = (7u32, 5u32);
+	let rgba: Vec<u16> = (0..width * height * 4).map(|i| (i * 997) as u16).collect();
+	let png = png16::PNG::from_rgba16(width, height, rgba.clone()).unwrap();
+	assert_eq!((png.ihdr.total_bytes, png.ihdr.depth, png.ihdr.color_type), (13, png16::DEPTH_16, png16::COLOR_TYPE_RGBA));
+	assert!(png.header.is_empty() && png.trailer.is_empty() && png.metadata.text.is_empty());
+
+	// Encodes Without Touching ihdr, Decodes To The Same Samples And IHDR
+	let mut out = vec![];
+	png16::encode_png_to_writer(&png, png16::DEPTH_16, &mut out).unwrap();
+	let decoded = png16::decode_16bit_png_from_bytes(&out).unwrap();
+	assert_eq!(decoded.image.samples, rgba);
+	assert_eq!((decoded.ihdr.width, decoded.ihdr.height, decoded.ihdr.crc), (png.ihdr.width, png.ihdr.height, png.ihdr.crc));
+
+	// Every Layout Round Trips In Its Own Color Type
+	type FromSamples = fn(u32, u32, Vec<u16>) -> png16::Result<png16::PNG>;
+	let constructors: [(FromSamples, usize, u8); 4] = [
+		(png16::PNG::from_rgb16, 3, png16::COLOR_TYPE_RGB),
+		(png16::PNG::from_gray_alpha16, 2, png16::COLOR_TYPE_GRAY_ALPHA),
+		(png16::PNG::from_gray16, 1, png16::COLOR_TYPE_GRAY),
+		(png16::PNG::from_rgba16, 4, png16::COLOR_TYPE_RGBA),
+	];
+	for &(from, channels, color_type) in constructors.iter() {
+		let samples = rgba[..width as usize * height as usize * channels].to_vec();
+		let png = from(width, height, samples.clone()).unwrap();
+		let mut out = vec![];
+		png16::encode_png_to_writer(&png, png16::DEPTH_16, &mut out).unwrap();
+		let decoded = png16::decode_16bit_png_from_bytes(&out).unwrap();
+		assert_eq!((decoded.ihdr.color_type, decoded.ihdr.crc), (color_type, png.ihdr.crc));
+		assert_eq!(decoded.image.samples, samples);
+
+		match from(width, height, samples[1..].to_vec()) {
+			Err(png16::Error::InvalidImage { reason }) => assert_eq!(reason, "Image Buffer Length Does Not Match Size"),
+			_ => panic!("Short Buffer Accepted"),
+		}
+	}
+
+	match png16::PNG::from_gray16(0, 1, vec![]) {
+		Err(png16::Error::InvalidImage { reason }) => assert_eq!(reason, "Width"),
+		_ => panic!("Zero Width Accepted"),
+	}
+	match png16::PNG::from_rgb16(1, png16::MAX_DIMENSION + 1, vec![]) {
+		Err(png16::Error::InvalidImage { reason }) => assert_eq!(reason, "Height"),
+		_ => panic!("Height Over MAX_DIMENSION Accepted"),
+	}
+
+	// from_image Takes Padded Rows
+	let mut image = png16::ImageBuffer::new(2, 2, png16::ChannelLayout::Gray);
+	image.stride = 3;
+	image.samples = vec![1, 2, 0, 3, 4];
+	let png = png16::PNG::from_image(image).unwrap();
+	assert_eq!(png.get_pixel::<png16::Luma16>(1, 1), png16::Luma16 { l: 4 });
+}